@@ -1,3 +1,5 @@
 mod notification_bus;
+mod render_worker;
 
 pub use notification_bus::*;
+pub use render_worker::*;
@@ -0,0 +1,292 @@
+use crate::{
+    highlight::{highlight, Highlight, Language},
+    output_format::{Format, FormatError},
+    scenario::Scenario,
+    template_engine::{
+        Engine, RenderedOutput, ScenarioEngine, SourceMap, TemplateEngine, TemplateError, Tracer,
+    },
+    InputsData,
+};
+use log::*;
+use serde::{Deserialize, Serialize};
+use yew::worker::*;
+
+/// Renders the outputs of the scenario in a web worker, so that rendering
+/// big templates doesn't block the page.
+///
+/// The worker is built from the `render_worker` binary. Messages are
+/// encoded with bincode, which can't decode JSON values, so scenarios and
+/// data are sent as JSON strings.
+pub struct RenderWorker {
+    link: AgentLink<Self>,
+    engine: ScenarioEngine,
+    scenario: Option<Scenario>,
+    /// Whether the outputs are rendered with their source maps.
+    tracing: bool,
+    /// Compiled at the first traced render of the scenario.
+    tracer: Option<Tracer>,
+    /// Why the last scenario couldn't be set, reported with each render.
+    scenario_error: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub enum RenderRequest {
+    /// Compiles the templates of a JSON scenario, with the strict mode
+    /// overriding its own, if any.
+    SetScenario {
+        scenario: String,
+        strict: Option<bool>,
+    },
+    /// Forgets the scenario, eg. when the restored one doesn't compile, so
+    /// that its outputs aren't rendered with the data of another one.
+    ClearScenario,
+    SetStrictMode(bool),
+    /// Enables the source maps of the Handlebars outputs, which need
+    /// another render.
+    SetTracing(bool),
+    /// Renders the outputs of the scenario with the JSON inputs data. The
+    /// response has the same sequence number.
+    Render {
+        sequence: u64,
+        data: String,
+    },
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct RenderResponse {
+    pub sequence: u64,
+    pub outputs: Vec<RenderedFile>,
+    /// Why the worker has no scenario to render, eg. it uses an input kind
+    /// which isn't registered in the worker.
+    pub error: Option<String>,
+}
+
+/// A [`RenderedOutput`] which can be sent between workers.
+///
+/// Outputs are highlighted here rather than by the page, which only has to
+/// lay out the highlighted ranges.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct RenderedFile {
+    pub file_name: String,
+    pub language: Option<Language>,
+    pub format: Option<Format>,
+    pub content: Result<String, RenderFailure>,
+    pub format_error: Option<FormatError>,
+    pub highlights: Vec<Highlight>,
+    pub source_map: Option<SourceMap>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct RenderFailure {
+    /// The whole chain of errors.
+    pub message: String,
+    pub error: Option<TemplateError>,
+}
+
+impl From<RenderedOutput> for RenderedFile {
+    fn from(output: RenderedOutput) -> Self {
+        let highlights = match (output.language, &output.content) {
+            (Some(language), Ok(content)) => highlight(language, content),
+            _ => vec![],
+        };
+        Self {
+            file_name: output.file_name,
+            language: output.language,
+            format: output.format,
+            content: output.content.map_err(|e| RenderFailure {
+                message: format!("{:#}", e),
+                error: e.downcast_ref::<TemplateError>().cloned(),
+            }),
+            format_error: output.format_error,
+            highlights,
+            source_map: None,
+        }
+    }
+}
+
+impl RenderWorker {
+    fn set_scenario(&mut self, scenario: &str, strict: Option<bool>) {
+        self.scenario = None;
+        self.tracer = None;
+        self.scenario_error = None;
+        let scenario: Scenario = match serde_json::from_str(scenario) {
+            Ok(scenario) => scenario,
+            Err(e) => {
+                return self.fail(format!("The render worker can't load the scenario: {}", e))
+            }
+        };
+        // The scenario has already been compiled successfully by the page.
+        if let Err(errors) = self.engine.set_scenario(&scenario) {
+            let errors: Vec<String> = errors.iter().map(ToString::to_string).collect();
+            return self.fail(format!(
+                "The render worker failed to compile: {}",
+                errors.join(" ")
+            ));
+        }
+        if let Some(strict) = strict {
+            self.engine.set_strict_mode(strict);
+        }
+        self.scenario = Some(scenario);
+    }
+
+    fn fail(&mut self, error: String) {
+        error!("{}", error);
+        self.scenario_error = Some(error);
+    }
+
+    fn render(&mut self, data: &str) -> Vec<RenderedFile> {
+        let (scenario, data) = match (&self.scenario, serde_json::from_str::<InputsData>(data)) {
+            (Some(scenario), Ok(data)) => (scenario, data),
+            (None, _) => return vec![],
+            (_, Err(e)) => {
+                error!("Invalid data sent to the render worker: {}", e);
+                return vec![];
+            }
+        };
+        let mut files: Vec<RenderedFile> = self
+            .engine
+            .render_outputs(scenario, &data)
+            .into_iter()
+            .map(RenderedFile::from)
+            .collect();
+        if self.tracing && scenario.engine == Engine::Handlebars {
+            trace(&mut self.tracer, scenario, &data, &mut files);
+        }
+        files
+    }
+}
+
+/// Adds their source maps to the outputs. They are left without one if
+/// the traced render differs, eg. when they are pretty-printed.
+fn trace(
+    tracer: &mut Option<Tracer>,
+    scenario: &Scenario,
+    data: &InputsData,
+    files: &mut [RenderedFile],
+) {
+    if tracer.is_none() {
+        *tracer = Tracer::new(scenario).ok();
+    }
+    let tracer = match tracer {
+        Some(tracer) => tracer,
+        None => return,
+    };
+    for (template, file) in scenario.outputs().zip(files) {
+        if let (Ok(content), Ok((traced, source_map))) =
+            (&file.content, tracer.render(&template.name, data))
+        {
+            if *content == traced {
+                file.source_map = Some(source_map);
+            }
+        }
+    }
+}
+
+impl Agent for RenderWorker {
+    type Reach = Public;
+    type Message = ();
+    type Input = RenderRequest;
+    type Output = RenderResponse;
+
+    fn create(link: AgentLink<Self>) -> Self {
+        Self {
+            link,
+            engine: ScenarioEngine::new_uninit(),
+            scenario: None,
+            tracing: false,
+            tracer: None,
+            scenario_error: None,
+        }
+    }
+
+    fn update(&mut self, _: Self::Message) {}
+
+    fn handle_input(&mut self, msg: Self::Input, sender: HandlerId) {
+        match msg {
+            RenderRequest::SetScenario { scenario, strict } => self.set_scenario(&scenario, strict),
+            RenderRequest::ClearScenario => {
+                self.scenario = None;
+                self.tracer = None;
+                self.scenario_error = None;
+            }
+            RenderRequest::SetStrictMode(strict) => self.engine.set_strict_mode(strict),
+            RenderRequest::SetTracing(tracing) => self.tracing = tracing,
+            RenderRequest::Render { sequence, data } => {
+                let outputs = self.render(&data);
+                let error = self.scenario_error.clone();
+                self.link.respond(
+                    sender,
+                    RenderResponse {
+                        sequence,
+                        outputs,
+                        error,
+                    },
+                );
+            }
+        }
+    }
+
+    fn name_of_resource() -> &'static str {
+        "render_worker.js"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Path;
+    use serde_json::json;
+
+    #[test]
+    fn rendered_files_keep_the_template_errors() {
+        let scenario: Scenario = serde_json::from_value(json!({
+            "templates": [
+                {"name": "a", "template": "{{a}}", "output": true},
+                {"name": "b", "template": "\n{{b}}", "output": true}
+            ],
+            "strict": true,
+            "inputs": []
+        }))
+        .unwrap();
+        let engine = ScenarioEngine::with_scenario(&scenario).ok().unwrap();
+        let files: Vec<RenderedFile> = engine
+            .render_outputs(&scenario, &json!({"a": 1}))
+            .into_iter()
+            .map(RenderedFile::from)
+            .collect();
+
+        assert_eq!(files[0].content, Ok("1".to_owned()));
+        let failure = files[1].content.as_ref().unwrap_err();
+        assert_eq!(
+            failure.message,
+            "Missing variable 'b' in template 'b' at line 2, column 1."
+        );
+        assert_eq!(failure.error.as_ref().unwrap().position, Some((2, 1)));
+    }
+
+    #[test]
+    fn source_maps_of_the_outputs() {
+        let scenario: Scenario = serde_json::from_value(json!({
+            "templates": [
+                {"name": "a", "template": "{{a}}", "output": true},
+                {"name": "b.json", "template": "[{{a}}]", "output": true, "format": "json", "pretty": true}
+            ],
+            "inputs": []
+        }))
+        .unwrap();
+        let data = InputsData::from(json!({"a": 1}));
+        let engine = ScenarioEngine::with_scenario(&scenario).ok().unwrap();
+        let mut files: Vec<RenderedFile> = engine
+            .render_outputs(&scenario, &data)
+            .into_iter()
+            .map(RenderedFile::from)
+            .collect();
+        trace(&mut None, &scenario, &data, &mut files);
+
+        let source_map = files[0].source_map.as_ref().unwrap();
+        assert_eq!(source_map.spans.len(), 1);
+        assert_eq!(source_map.spans[0].reads, vec![Path::from("a")]);
+        // The pretty-printed output differs from the traced one.
+        assert_eq!(files[1].source_map, None);
+    }
+}
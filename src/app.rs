@@ -1,17 +1,37 @@
 use crate::{
-    agents::{NotificationBus, NotificationSender},
-    components::{Navbar, Notifications},
+    agents::{
+        NotificationBus, NotificationSender, RenderFailure, RenderRequest, RenderResponse,
+        RenderWorker, RenderedFile,
+    },
+    batch::{render_rows, RenderedRow},
+    components::{ImportModal, Navbar, Notifications, TemplateEditor},
+    diff::{diff_lines, DiffLine, LineChange},
+    download::download_file,
+    highlight::Highlight,
+    json_schema,
+    migrations::{UnsupportedVersion, SCENARIO, SESSION},
+    output_format::{Format, FormatError},
     prelude::*,
-    scenario::Scenario,
-    template_engine::{HandlebarsEngine, TemplateEngine},
+    scenario::{Metadata, Scenario, Template},
+    scenario_tests::{run_tests, OutputMismatch, TestReport},
+    template_analysis,
+    template_engine::{
+        template_source, CompileError, Engine, LineSources, ScenarioEngine, SourceMap,
+    },
+    views::{render_code, render_diff, CodeMarks},
+    zip::zip_files,
     InputsData, Path,
 };
 use lazy_static::lazy_static;
 use serde::{Deserialize, Serialize};
+use std::{mem, time::Duration};
 use yew::{
-    agent::{Dispatched, Dispatcher},
+    agent::{Bridge, Bridged, Dispatched, Dispatcher},
     format::Json as YewJson,
-    services::storage::{Area, StorageService},
+    services::{
+        storage::{Area, StorageService},
+        timeout::{TimeoutService, TimeoutTask},
+    },
     Component, ComponentLink, Html, ShouldRender,
 };
 
@@ -25,25 +45,103 @@ lazy_static! {
 const JSON_INPUT: &str = include_str!("input_data.json");
 const INPUT_TEMPLATE: &str = include_str!("input_template.hbs");
 
+/// Title of the browser tab, as in `index.html`.
+const DOCUMENT_TITLE: &str = "Live Handlebars • WebAssembly + Rust";
+
+/// Delay without edits after which the edited templates are recompiled.
+const RECOMPILE_DELAY: Duration = Duration::from_millis(300);
+
+/// Delay after which a render which hasn't been answered is reported.
+const RENDER_TIMEOUT: Duration = Duration::from_secs(5);
+
 pub struct App {
     link: ComponentLink<Self>,
-    template_engine: HandlebarsEngine,
+    /// Compiles the templates to report their errors right away, the
+    /// render worker renders them.
+    template_engine: ScenarioEngine,
+    render_worker: Box<dyn Bridge<RenderWorker>>,
+    /// Sequence number of the last render request.
+    render_sequence: u64,
+    /// Whether the render worker has the loaded scenario. Nothing is
+    /// rendered otherwise, until its templates compile.
+    worker_has_scenario: bool,
+    /// The outputs of the last answered render request.
+    rendered: Vec<RenderedFile>,
+    /// The outputs before the last render which changed them.
+    previous_rendered: Vec<RenderedFile>,
+    /// Outputs pinned to be diffed with instead of the previous ones.
+    baseline: Option<Vec<RenderedFile>>,
+    /// Whether the selected output is shown as a diff.
+    diff_mode: bool,
+    /// The diff of the selected output, computed when it changes rather
+    /// than at each view.
+    diff: Vec<DiffLine>,
+    /// Whether the outputs are rendered with their source maps.
+    tracing: bool,
+    /// The line of the selected output whose sources are shown.
+    selected_line: Option<usize>,
+    /// The input whose fragments of the outputs are marked.
+    hovered_input: Option<Path>,
+    /// The inputs edited since the scenario was loaded, whose validation
+    /// errors are shown.
+    edited_inputs: Vec<Path>,
+    /// Started when a render request is sent while none is pending, and
+    /// cancelled when the last one is answered.
+    render_timeout: Option<TimeoutTask>,
+    render_timed_out: bool,
+    /// Why the render worker can't render the scenario.
+    render_error: Option<String>,
     storage: StorageService,
+    timeout: TimeoutService,
     notification_bus: Dispatcher<NotificationBus>,
     state: State,
     on_navevent: Callback<NavEvent>,
+    import_dialog: Option<ImportKind>,
+    selected_output: usize,
+    /// Strict mode chosen for this session, overriding the scenarios' one.
+    strict_mode: Option<bool>,
+    /// The pending recompilation of the edited templates.
+    recompile_task: Option<TimeoutTask>,
+    /// Why the edited templates don't compile. The previous templates are
+    /// still rendered meanwhile.
+    template_errors: Vec<String>,
+    /// The results of the last run of the scenario's tests.
+    test_reports: Option<Vec<TestReport>>,
+    /// The outputs rendered for each row of an imported CSV file.
+    batch: Option<Vec<RenderedRow>>,
 }
 
 #[derive(Serialize, Deserialize, Debug)]
 enum State {
     Init,
     Loaded {
-        scenario: Scenario,
+        scenario: Box<Scenario>,
         #[serde(default)]
         inputs_data: InputsData,
     },
 }
 
+/// The state saved in local storage, with the version of its format, see
+/// [`crate::migrations`].
+#[derive(Serialize, Deserialize)]
+struct SavedSession<S> {
+    format_version: u64,
+    state: S,
+}
+
+/// Restores the state saved in local storage, migrating it from older
+/// versions of the app.
+fn restore_state(mut session: JsonValue) -> Result<State> {
+    SESSION.migrate(&mut session)?;
+    // The scenario is also migrated when deserialized, but a newer one
+    // must be reported as such, rather than as an invalid session.
+    if let Some(scenario) = session.pointer_mut("/state/Loaded/scenario") {
+        SCENARIO.migrate(scenario)?;
+    }
+    let session: SavedSession<State> = serde_json::from_value(session)?;
+    Ok(session.state)
+}
+
 #[derive(Debug)]
 pub enum Msg {
     Init,
@@ -53,15 +151,47 @@ pub enum Msg {
     EditedInput(Path, JsonValue),
     ListInputSizeChanged(Path, usize),
     RemoveAt(Path),
+    Import(ImportKind, String),
+    CloseImportDialog,
+    SelectOutput(usize),
+    DownloadOutputs,
+    ToggleStrictMode,
+    EditedTemplate(usize, String),
+    RecompileTemplates,
+    Rendered(RenderResponse),
+    RenderTimedOut,
+    ToggleDiffMode,
+    TogglePinnedBaseline,
+    ToggleTracing,
+    SelectLine(usize),
+    HoverInput(Option<Path>),
+    RunTests,
+    DownloadBatch,
+    DownloadBatchRow(usize),
+    EditBatchRow(usize),
+    CloseBatch,
 }
 
 #[derive(Debug)]
 pub enum NavEvent {
     LoadDebugScenario,
     LoadFromLocalStorage,
+    CreateFromJsonSchema,
+    CreateFromTemplate,
+    ExportJsonSchema,
+    RenderCsvBatch,
     UnloadScenario,
 }
 
+/// The kinds of documents a scenario can be created from.
+#[derive(Debug, Clone, Copy)]
+pub enum ImportKind {
+    JsonSchema,
+    HandlebarsTemplate,
+    /// Not a scenario but the rows of data to render it with.
+    CsvBatch,
+}
+
 impl NotificationSender for App {
     fn notification_bus(&mut self) -> &mut Dispatcher<NotificationBus> {
         &mut self.notification_bus
@@ -76,13 +206,38 @@ impl Component for App {
         link.send_message(Msg::Init);
         let on_navevent = link.callback(Msg::NavEvent);
 
+        let render_worker = RenderWorker::bridge(link.callback(Msg::Rendered));
+
         Self {
             link,
-            template_engine: HandlebarsEngine::new_uninit(),
+            template_engine: ScenarioEngine::new_uninit(),
+            render_worker,
+            render_sequence: 0,
+            worker_has_scenario: false,
+            rendered: vec![],
+            previous_rendered: vec![],
+            baseline: None,
+            diff_mode: false,
+            diff: vec![],
+            tracing: false,
+            selected_line: None,
+            hovered_input: None,
+            edited_inputs: vec![],
+            render_timeout: None,
+            render_timed_out: false,
+            render_error: None,
             storage: StorageService::new(Area::Local).expect("Failed to get localStorage."),
+            timeout: TimeoutService::new(),
             notification_bus: NotificationBus::dispatcher(),
             state: State::Init,
             on_navevent,
+            import_dialog: None,
+            selected_output: 0,
+            strict_mode: None,
+            recompile_task: None,
+            template_errors: vec![],
+            test_reports: None,
+            batch: None,
         }
     }
 
@@ -91,12 +246,26 @@ impl Component for App {
         match msg {
             Msg::Init => {
                 self.state = State::Init;
+                self.update_document_title();
                 true
             }
             Msg::NavEvent(nav_event) => {
                 match nav_event {
                     NavEvent::LoadDebugScenario => self.load_debug_scenario(),
                     NavEvent::LoadFromLocalStorage => self.load_from_local_storage(),
+                    NavEvent::CreateFromJsonSchema => {
+                        self.import_dialog = Some(ImportKind::JsonSchema);
+                        true
+                    }
+                    NavEvent::CreateFromTemplate => {
+                        self.import_dialog = Some(ImportKind::HandlebarsTemplate);
+                        true
+                    }
+                    NavEvent::ExportJsonSchema => self.export_json_schema(),
+                    NavEvent::RenderCsvBatch => {
+                        self.import_dialog = Some(ImportKind::CsvBatch);
+                        true
+                    }
                     NavEvent::UnloadScenario => self.unload_scenario(),
                 }
             }
@@ -110,12 +279,19 @@ impl Component for App {
                 }
             },
             Msg::SaveToLocalStorage => {
+                let session = SavedSession {
+                    format_version: SESSION.current_version(),
+                    state: &self.state,
+                };
                 self.storage
-                    .store(LOCAL_STORAGE_KEY.as_ref(), YewJson(&self.state));
+                    .store(LOCAL_STORAGE_KEY.as_ref(), YewJson(&session));
                 false
             }
             Msg::EditedInput(path, value) => match &mut self.state {
                 State::Loaded { inputs_data, .. } => {
+                    if !self.edited_inputs.contains(&path) {
+                        self.edited_inputs.push(path.clone());
+                    }
                     match inputs_data.insert_at(&path, value) {
                         Ok(()) => self.link.send_message(Msg::SaveToLocalStorage),
                         Err(e) => {
@@ -123,6 +299,7 @@ impl Component for App {
                             error!("Failed to save value of '{}': {:?}", path, e);
                         }
                     }
+                    self.request_render();
                     true
                 }
                 _ => {
@@ -140,6 +317,7 @@ impl Component for App {
                     }
 
                     self.link.send_message(Msg::SaveToLocalStorage);
+                    self.request_render();
                     true
                 }
                 _ => {
@@ -158,6 +336,7 @@ impl Component for App {
                     }
 
                     self.link.send_message(Msg::SaveToLocalStorage);
+                    self.request_render();
                     true
                 }
                 _ => {
@@ -168,6 +347,140 @@ impl Component for App {
                     false
                 }
             },
+            Msg::Import(kind, text) => match self.import(kind, &text) {
+                Ok(_) => {
+                    self.import_dialog = None;
+                    true
+                }
+                Err(e) => {
+                    let error = match kind {
+                        ImportKind::CsvBatch => e.context("Failed to render the batch."),
+                        _ => e.context("Failed to create the scenario."),
+                    };
+                    self.notif_error(format!("{:?}", error));
+                    false
+                }
+            },
+            Msg::CloseImportDialog => {
+                self.import_dialog = None;
+                true
+            }
+            Msg::SelectOutput(index) => {
+                self.selected_output = index;
+                self.selected_line = None;
+                self.update_diff();
+                true
+            }
+            Msg::DownloadOutputs => self.download_outputs(),
+            Msg::ToggleStrictMode => {
+                self.strict_mode = Some(!self.is_strict());
+                self.template_engine.set_strict_mode(self.is_strict());
+                self.render_worker
+                    .send(RenderRequest::SetStrictMode(self.is_strict()));
+                self.request_render();
+                true
+            }
+            Msg::EditedTemplate(index, source) => {
+                if let State::Loaded { scenario, .. } = &mut self.state {
+                    if let Some(template) = scenario.templates.get_mut(index) {
+                        template.template = Template::StringTemplate(source);
+                    }
+                }
+                // Replacing the task cancels the previous one.
+                self.recompile_task = Some(self.timeout.spawn(
+                    RECOMPILE_DELAY,
+                    self.link.callback(|_| Msg::RecompileTemplates),
+                ));
+                false
+            }
+            Msg::RecompileTemplates => self.recompile_templates(),
+            Msg::Rendered(response) => {
+                // Stale renders are dropped, the last one is on its way.
+                if response.sequence != self.render_sequence || !self.worker_has_scenario {
+                    return false;
+                }
+                if response.outputs != self.rendered {
+                    self.previous_rendered = mem::replace(&mut self.rendered, response.outputs);
+                    self.update_diff();
+                }
+                self.render_timeout = None;
+                self.render_timed_out = false;
+                self.render_error = response.error;
+                true
+            }
+            Msg::RenderTimedOut => {
+                self.render_timed_out = self.render_timeout.take().is_some();
+                self.render_timed_out
+            }
+            Msg::ToggleDiffMode => {
+                self.diff_mode = !self.diff_mode;
+                self.update_diff();
+                true
+            }
+            Msg::TogglePinnedBaseline => {
+                self.baseline = match self.baseline {
+                    Some(_) => None,
+                    None => Some(self.rendered.clone()),
+                };
+                self.update_diff();
+                true
+            }
+            Msg::ToggleTracing => {
+                self.tracing = !self.tracing;
+                self.selected_line = None;
+                self.render_worker
+                    .send(RenderRequest::SetTracing(self.tracing));
+                self.request_render();
+                true
+            }
+            Msg::SelectLine(line) => {
+                self.selected_line = Some(line).filter(|_| self.selected_line != Some(line));
+                true
+            }
+            Msg::HoverInput(path) => {
+                let changed = self.hovered_input != path;
+                self.hovered_input = path;
+                changed && self.tracing
+            }
+            Msg::RunTests => self.run_tests(),
+            Msg::DownloadBatch => {
+                if let Some(rows) = &self.batch {
+                    let files: Vec<(String, &str)> =
+                        rows.iter().flat_map(RenderedRow::files).collect();
+                    if let Err(e) = download_zip("batch.zip", &files) {
+                        self.notif_error(format!("Can't download the batch: {}", e));
+                    }
+                }
+                false
+            }
+            Msg::DownloadBatchRow(index) => {
+                if let Some(row) = self.batch.as_ref().and_then(|rows| rows.get(index)) {
+                    if let Err(e) = download_zip(&format!("row-{}.zip", row.row), &row.files()) {
+                        self.notif_error(format!("Can't download the row: {}", e));
+                    }
+                }
+                false
+            }
+            Msg::EditBatchRow(index) => {
+                let data = self
+                    .batch
+                    .as_ref()
+                    .and_then(|rows| rows.get(index))
+                    .map(|row| row.data.clone());
+                match (&mut self.state, data) {
+                    (State::Loaded { inputs_data, .. }, Some(data)) => {
+                        *inputs_data = data;
+                        self.link.send_message(Msg::SaveToLocalStorage);
+                        self.request_render();
+                        true
+                    }
+                    _ => false,
+                }
+            }
+            Msg::CloseBatch => {
+                self.batch = None;
+                true
+            }
         }
     }
 
@@ -188,10 +501,12 @@ impl Component for App {
                 html! {
                     <div class="columns is-desktop">
                         <div class="column">
-                            { render_inputs(&scenario.inputs, inputs_data, &self.link) }
+                            { render_metadata(scenario) }
+                            { render_inputs(&scenario.inputs, inputs_data, &self.edited_inputs, &self.link) }
                         </div>
                         <div class="column">
-                            { render_code_column(inputs_data, &self.template_engine) }
+                            { self.render_template_editor(scenario) }
+                            { self.render_code_column(scenario, inputs_data) }
                         </div>
                     </div>
                 }
@@ -201,6 +516,7 @@ impl Component for App {
         html! {
             <>
                 <Notifications />
+                { self.render_import_dialog() }
 
                 <div class="section">
                     <div class="container navbar-container">
@@ -228,26 +544,75 @@ impl Component for App {
 
 impl App {
     fn load_from_json(&mut self, json_str: &str) -> Result<ShouldRender> {
-        let mut json_data: JsonValue = serde_json::from_str(&json_str).context("Invalid JSON.")?;
-        let template = serde_json::from_value(json_data["template"].take())
-            .context("JSON input must have a template.")?;
-
-        let inputs = serde_json::from_value(json_data["inputs"].take())
-            .context("Failed to deserialize inputs")?;
+        let scenario: Scenario = serde_json::from_str(json_str).context("Invalid scenario.")?;
+        self.load_scenario(scenario)
+    }
 
-        self.template_engine
-            .set_template(&template)
-            .map_err(|e| e.context("Failed to load the template"))?;
+    fn load_scenario(&mut self, scenario: Scenario) -> Result<ShouldRender> {
+        if let Err(errors) = self.template_engine.set_scenario(&scenario) {
+            let count = errors.len();
+            for error in errors {
+                self.notif_error(error.to_string());
+            }
+            bail!("{} template(s) failed to compile.", count);
+        }
+        if let Some(strict) = self.strict_mode {
+            self.template_engine.set_strict_mode(strict);
+        }
 
         self.state = State::Loaded {
-            scenario: Scenario { template, inputs },
-            inputs_data: InputsData::default(),
+            inputs_data: default_data(&scenario.inputs),
+            scenario: Box::new(scenario),
         };
+        self.selected_output = 0;
+        self.edited_inputs.clear();
+        self.rendered.clear();
+        self.previous_rendered.clear();
+        self.baseline = None;
+        self.recompile_task = None;
+        self.template_errors.clear();
+        self.test_reports = None;
+        self.batch = None;
+        self.update_document_title();
+        self.send_scenario_to_worker();
+        self.lint_scenario();
         self.link.send_message(Msg::SaveToLocalStorage);
 
         Ok(true)
     }
 
+    fn import(&mut self, kind: ImportKind, text: &str) -> Result<ShouldRender> {
+        let scenario = match kind {
+            ImportKind::JsonSchema => {
+                let schema: JsonValue = serde_json::from_str(text).context("Invalid JSON.")?;
+                let mut scenario = Scenario::new(
+                    Template::StringTemplate(
+                        "{{! Generated from a JSON Schema, write your template here. }}".into(),
+                    ),
+                    json_schema::inputs_from_json_schema(&schema)?,
+                );
+                let field = |name| {
+                    schema
+                        .get(name)
+                        .and_then(JsonValue::as_str)
+                        .map(str::to_owned)
+                };
+                scenario.metadata = Metadata {
+                    title: field("title"),
+                    description: field("description"),
+                    ..Metadata::default()
+                };
+                scenario
+            }
+            ImportKind::HandlebarsTemplate => Scenario::new(
+                Template::StringTemplate(text.to_owned()),
+                template_analysis::infer_inputs(text)?,
+            ),
+            ImportKind::CsvBatch => return self.render_batch(text),
+        };
+        self.load_scenario(scenario)
+    }
+
     fn load_debug_scenario(&mut self) -> ShouldRender {
         let json_str = JSON_INPUT.replace("%TEMPLATE%", &INPUT_TEMPLATE.replace("\n", "\\n"));
         self.link.send_message(Msg::FetchedJsonData(json_str));
@@ -255,49 +620,900 @@ impl App {
     }
 
     fn load_from_local_storage(&mut self) -> ShouldRender {
-        if let YewJson(Ok(restored_state)) = self.storage.restore(LOCAL_STORAGE_KEY.as_ref()) {
-            self.state = restored_state;
-
-            // Initialize the template engine with the deserialized template.
-            // This can fail if the restored state is somewhat invalid.
-            if let State::Loaded { scenario, .. } = &self.state {
-                if let Err(e) = self.template_engine.set_template(&scenario.template) {
-                    self.storage.remove(LOCAL_STORAGE_KEY.as_ref());
-                    self.state = State::Init;
-                    self.link.send_message(Msg::Init);
-
-                    self.notif_error(format!(
-                        "Invalid template fetched from local storage: {}",
-                        e
-                    ));
+        let YewJson(session): YewJson<Result<JsonValue>> =
+            self.storage.restore(LOCAL_STORAGE_KEY.as_ref());
+        match session.and_then(restore_state) {
+            Ok(restored_state) => {
+                self.state = restored_state;
+                self.edited_inputs.clear();
+
+                // Initialize the template engine with the deserialized templates.
+                // They may have been saved while being edited and not compile,
+                // in which case they are restored in the editor with the errors.
+                self.recompile_task = None;
+                self.template_errors.clear();
+                self.test_reports = None;
+                self.batch = None;
+                if let State::Loaded { scenario, .. } = &self.state {
+                    if let Err(errors) = self.template_engine.set_scenario(scenario) {
+                        self.template_engine = ScenarioEngine::new_uninit();
+                        self.template_errors = describe_compile_errors(scenario, &errors);
+                        self.rendered.clear();
+                        self.clear_worker_scenario();
+                        self.notif_warn("The restored templates don't compile.");
+                    } else {
+                        if let Some(strict) = self.strict_mode {
+                            self.template_engine.set_strict_mode(strict);
+                        }
+                        self.send_scenario_to_worker();
+                    }
+                }
+
+                self.update_document_title();
+                if let State::Loaded { scenario, .. } = &self.state {
+                    let title = scenario.title().to_owned();
+                    self.notif_success(format!("Restored '{}'.", title));
+                    self.lint_scenario();
                 }
+
+                true
+            }
+            Err(e) if e.is::<UnsupportedVersion>() => {
+                // The session is kept for the newer version of the app.
+                self.notif_error(e.to_string());
+                self.link.send_message(Msg::Init);
+                false
+            }
+            Err(_) => {
+                // If we're here, local storage is either absent or invalid
+                self.notif_warn("Nothing to restore from local storage.");
+                self.storage.remove(LOCAL_STORAGE_KEY.as_ref());
+                self.link.send_message(Msg::Init);
+                false
+            }
+        }
+    }
+
+    /// Warns about likely mistakes in the loaded scenario, like template
+    /// variables which don't match any input. Only Handlebars templates
+    /// are analyzed.
+    fn lint_scenario(&mut self) {
+        let lints = match &self.state {
+            State::Loaded { scenario, .. } if scenario.engine == Engine::Handlebars => {
+                template_analysis::lint_scenario(scenario)
+            }
+            State::Loaded { .. } | State::Init => return,
+        };
+
+        match lints {
+            Ok(lints) if lints.is_empty() => {}
+            Ok(lints) => {
+                let lints: Vec<String> = lints.iter().map(ToString::to_string).collect();
+                self.notif_warn(format!(
+                    "The scenario may contain mistakes:\n{}",
+                    lints.join("\n")
+                ));
+            }
+            Err(e) => warn!("Failed to lint the scenario: {:?}", e),
+        }
+    }
+
+    fn export_json_schema(&mut self) -> ShouldRender {
+        match &self.state {
+            State::Loaded { scenario, .. } => {
+                let schema = format!("{:#}", scenario.inputs_json_schema());
+                download_file(
+                    "inputs.schema.json",
+                    "application/schema+json",
+                    schema.as_bytes(),
+                );
+            }
+            State::Init => self.notif_warn("Load a scenario to export its inputs."),
+        }
+        false
+    }
+
+    /// Whether the templates are rendered in strict mode, either as chosen
+    /// for this session or as set by the scenario.
+    fn is_strict(&self) -> bool {
+        match (&self.state, self.strict_mode) {
+            (_, Some(strict)) => strict,
+            (State::Loaded { scenario, .. }, None) => scenario.strict,
+            (State::Init, None) => false,
+        }
+    }
+
+    /// The index of the selected output, which may have been removed since
+    /// it was selected.
+    fn selected_output(&self) -> usize {
+        self.selected_output
+            .min(self.rendered.len().saturating_sub(1))
+    }
+
+    /// Diffs the selected output with the same file in the baseline, or in
+    /// the previous outputs. Files missing from them are all inserted.
+    fn update_diff(&mut self) {
+        self.diff.clear();
+        if !self.diff_mode {
+            return;
+        }
+        let (file_name, content) = match self.rendered.get(self.selected_output()) {
+            Some(RenderedFile {
+                file_name,
+                content: Ok(content),
+                ..
+            }) => (file_name, content),
+            _ => return,
+        };
+        let base = self.baseline.as_ref().unwrap_or(&self.previous_rendered);
+        let base_content = base
+            .iter()
+            .find(|base_output| &base_output.file_name == file_name)
+            .and_then(|base_output| base_output.content.as_ref().ok());
+        self.diff = diff_lines(base_content.map_or("", String::as_str), content);
+    }
+
+    /// Downloads all the rendered outputs as a zip archive, unless some of
+    /// them fail to render or their file names can't be in an archive.
+    fn download_outputs(&mut self) -> ShouldRender {
+        if self.render_timeout.is_some() {
+            self.notif_warn("The outputs are still being rendered, try again later.");
+            return false;
+        }
+
+        let outputs = &self.rendered;
+        let failed: Vec<&str> = outputs
+            .iter()
+            .filter(|output| output.content.is_err())
+            .map(|output| output.file_name.as_str())
+            .collect();
+        if !failed.is_empty() {
+            self.notif_error(format!(
+                "Can't download the outputs, some failed to render: {}.",
+                failed.join(", ")
+            ));
+            return false;
+        }
+
+        let archive = zip_files(outputs.iter().filter_map(|output| {
+            let content = output.content.as_ref().ok()?;
+            Some((output.file_name.as_str(), content.as_bytes()))
+        }));
+        match archive {
+            Ok(archive) => download_file("outputs.zip", "application/zip", &archive),
+            Err(e) => self.notif_error(format!("Can't download the outputs: {}", e)),
+        }
+        false
+    }
+
+    /// Compiles the edited templates, keeping the previous ones if they
+    /// fail to compile, and saves them either way.
+    fn recompile_templates(&mut self) -> ShouldRender {
+        self.recompile_task = None;
+        let scenario = match &self.state {
+            State::Loaded { scenario, .. } => scenario,
+            State::Init => return false,
+        };
+
+        match self.template_engine.set_scenario(scenario) {
+            Ok(()) => {
+                self.template_errors.clear();
+                if let Some(strict) = self.strict_mode {
+                    self.template_engine.set_strict_mode(strict);
+                }
+                self.send_scenario_to_worker();
             }
+            Err(errors) => self.template_errors = describe_compile_errors(scenario, &errors),
+        }
+        self.link.send_message(Msg::SaveToLocalStorage);
+        true
+    }
 
-            if let State::Init = self.state {
-                // No notification
-            } else {
-                self.notif_success("Restored previous session.");
+    /// Sends the loaded scenario, which must compile, to the render worker
+    /// and renders it.
+    fn send_scenario_to_worker(&mut self) {
+        let scenario = match &self.state {
+            State::Loaded { scenario, .. } => serde_json::to_string(scenario),
+            State::Init => return,
+        };
+        match scenario {
+            Ok(scenario) => {
+                self.render_worker.send(RenderRequest::SetScenario {
+                    scenario,
+                    strict: self.strict_mode,
+                });
+                self.worker_has_scenario = true;
+                self.request_render();
             }
+            Err(e) => error!("Failed to serialize the scenario: {:?}", e),
+        }
+    }
+
+    /// Makes the render worker forget its scenario, and drops the answers
+    /// to the pending render requests.
+    fn clear_worker_scenario(&mut self) {
+        self.render_worker.send(RenderRequest::ClearScenario);
+        self.worker_has_scenario = false;
+        self.render_sequence += 1;
+        self.render_timeout = None;
+        self.render_timed_out = false;
+        self.render_error = None;
+    }
+
+    /// Asks the render worker to render the current data. Only the answer
+    /// to the last request is kept.
+    fn request_render(&mut self) {
+        let data = match &self.state {
+            State::Loaded { inputs_data, .. } if self.worker_has_scenario => {
+                serde_json::to_string(inputs_data)
+            }
+            State::Loaded { .. } | State::Init => return,
+        };
+        let data = match data {
+            Ok(data) => data,
+            Err(e) => return error!("Failed to serialize the inputs data: {:?}", e),
+        };
+
+        self.render_sequence += 1;
+        self.render_worker.send(RenderRequest::Render {
+            sequence: self.render_sequence,
+            data,
+        });
+        if self.render_timeout.is_none() {
+            self.render_timeout = Some(
+                self.timeout
+                    .spawn(RENDER_TIMEOUT, self.link.callback(|_| Msg::RenderTimedOut)),
+            );
+        }
+    }
 
-            true
+    /// Shows the title of the loaded scenario in the browser tab.
+    fn update_document_title(&self) {
+        let title = match &self.state {
+            State::Loaded { scenario, .. } => format!("{} • {}", scenario.title(), DOCUMENT_TITLE),
+            State::Init => DOCUMENT_TITLE.to_owned(),
+        };
+        stdweb::web::document().set_title(&title);
+    }
+
+    /// Renders the outputs of the scenario for each row of a CSV file, with
+    /// the templates as edited, compiled anew as the template engine of the
+    /// app lags behind the editor.
+    fn render_batch(&mut self, csv: &str) -> Result<ShouldRender> {
+        let scenario = match &self.state {
+            State::Loaded { scenario, .. } => scenario,
+            State::Init => bail!("Load a scenario before rendering a batch."),
+        };
+        let mut engine = ScenarioEngine::with_scenario(scenario).map_err(|errors| {
+            anyhow!(
+                "The batch can't render, {} template(s) failed to compile.",
+                errors.len()
+            )
+        })?;
+        if let Some(strict) = self.strict_mode {
+            engine.set_strict_mode(strict);
+        }
+        let rows = render_rows(&engine, scenario, csv)?;
+        let failed = rows.iter().filter(|row| !row.succeeded()).count();
+        if failed == 0 {
+            self.notif_success(format!("Rendered {} row(s).", rows.len()));
         } else {
-            // If we're here, local storage is either absent or invalid
-            self.notif_warn("Nothing to restore from local storage.");
-            self.storage.remove(LOCAL_STORAGE_KEY.as_ref());
-            self.link.send_message(Msg::Init);
-            false
+            self.notif_warn(format!("{} of the {} row(s) failed.", failed, rows.len()));
         }
+        self.batch = Some(rows);
+        Ok(true)
+    }
+
+    /// Runs the tests of the scenario with its templates as edited.
+    fn run_tests(&mut self) -> ShouldRender {
+        let reports = match &self.state {
+            State::Loaded { scenario, .. } => run_tests(scenario),
+            State::Init => return false,
+        };
+        match reports {
+            Ok(reports) => self.test_reports = Some(reports),
+            Err(errors) => {
+                self.test_reports = None;
+                self.notif_error(format!(
+                    "The tests can't run, {} template(s) failed to compile.",
+                    errors.len()
+                ));
+            }
+        }
+        true
     }
 
     fn unload_scenario(&mut self) -> ShouldRender {
         self.link.send_message(Msg::Init);
         false
     }
+
+    fn render_import_dialog(&self) -> Html {
+        let kind = match self.import_dialog {
+            Some(kind) => kind,
+            None => return html! {},
+        };
+        let (title, placeholder) = match kind {
+            ImportKind::JsonSchema => (
+                "Create scenario from JSON Schema",
+                r#"{ "type": "object", "properties": { ... } }"#,
+            ),
+            ImportKind::HandlebarsTemplate => (
+                "Create scenario from Handlebars template",
+                "Hello {{name}}!",
+            ),
+            ImportKind::CsvBatch => (
+                "Render a batch from CSV",
+                "name,persons.0.first_name,persons.1.first_name\nBob,Ann,Tom",
+            ),
+        };
+
+        html! {
+            <ImportModal
+                title=title
+                placeholder=placeholder
+                on_import=self.link.callback(move |text| Msg::Import(kind, text))
+                on_close=self.link.callback(|_| Msg::CloseImportDialog)
+                />
+        }
+    }
+
+    fn render_template_editor(&self, scenario: &Scenario) -> Html {
+        let templates: Vec<(String, String)> = scenario
+            .templates
+            .iter()
+            .map(|template| {
+                (
+                    template.name.clone(),
+                    template.template.source().into_owned(),
+                )
+            })
+            .collect();
+        html! {
+            <TemplateEditor
+                templates=templates
+                errors=self.template_errors.clone()
+                on_edit=self.link.callback(|(index, source)| Msg::EditedTemplate(index, source))
+                />
+        }
+    }
+
+    /// Shows an output, with the fragments written from the hovered input
+    /// marked and the sources of the selected line when it is traced.
+    fn render_traced_code(
+        &self,
+        scenario: &Scenario,
+        content: &str,
+        highlights: &[Highlight],
+        source_map: Option<&SourceMap>,
+    ) -> Html {
+        let source_map = match (self.tracing, source_map) {
+            (false, _) => return render_code(content, highlights, &CodeMarks::default()),
+            (true, Some(source_map)) => source_map,
+            (true, None) => {
+                return html! {
+                    <>
+                        <p class="help">
+                            { "This output has no source map: only Handlebars outputs which \
+                               aren't pretty-printed are traced." }
+                        </p>
+                        { render_code(content, highlights, &CodeMarks::default()) }
+                    </>
+                }
+            }
+        };
+
+        let marks = CodeMarks {
+            ranges: match &self.hovered_input {
+                Some(path) => source_map.fragments_reading(path),
+                None => vec![],
+            },
+            selected_line: self.selected_line,
+            on_click_line: Some(self.link.callback(Msg::SelectLine)),
+        };
+        let sources = match self.selected_line {
+            Some(line) => {
+                render_line_sources(scenario, line, &source_map.line_sources(content, line))
+            }
+            None => html! {
+                <p class="help">
+                    { "Click a line to see where it comes from, or hover an input to mark \
+                       what it writes." }
+                </p>
+            },
+        };
+        html! {
+            <>
+                { sources }
+                { render_code(content, highlights, &marks) }
+            </>
+        }
+    }
+
+    /// Shows the diff of the selected output, with the button to pin or
+    /// unpin the baseline.
+    fn render_diff(&self) -> Html {
+        let count = |change| {
+            self.diff
+                .iter()
+                .filter(|line| line.change == change)
+                .count()
+        };
+        let (base, pin) = match self.baseline {
+            Some(_) => ("the pinned baseline", "Unpin baseline"),
+            None => ("the previous render", "Pin as baseline"),
+        };
+        html! {
+            <>
+                <div class="level">
+                    <div class="level-left">
+                        <p class="level-item help">
+                            { format!(
+                                "{} line(s) inserted and {} removed since {}.",
+                                count(LineChange::Inserted),
+                                count(LineChange::Removed),
+                                base
+                            ) }
+                        </p>
+                    </div>
+                    <div class="level-right">
+                        <button
+                            class="button is-small level-item"
+                            onclick=self.link.callback(|_| Msg::TogglePinnedBaseline)>
+                            { pin }
+                        </button>
+                    </div>
+                </div>
+                { render_diff(&self.diff) }
+            </>
+        }
+    }
+
+    fn render_code_column(&self, scenario: &Scenario, inputs_data: &InputsData) -> Html {
+        let outputs = &self.rendered;
+        let selected = self.selected_output();
+        let rendered = match outputs.get(selected) {
+            Some(RenderedFile { content: Ok(_), .. }) if self.diff_mode => self.render_diff(),
+            Some(RenderedFile {
+                content: Ok(content),
+                highlights,
+                source_map,
+                ..
+            }) => self.render_traced_code(scenario, content, highlights, source_map.as_ref()),
+            Some(RenderedFile {
+                content: Err(failure),
+                ..
+            }) => render_template_error(scenario, failure),
+            None => html! { <pre></pre> },
+        };
+        let format_status = match outputs.get(selected) {
+            Some(RenderedFile {
+                format: Some(format),
+                content: Ok(content),
+                format_error,
+                ..
+            }) => render_format_status(*format, format_error.as_ref(), content),
+            _ => html! {},
+        };
+        let timed_out = if self.render_timed_out {
+            html! {
+                <div class="notification is-warning">
+                    { format!(
+                        "Rendering takes more than {} seconds, the outputs below are outdated.",
+                        RENDER_TIMEOUT.as_secs()
+                    ) }
+                </div>
+            }
+        } else {
+            html! {}
+        };
+        let render_error = match &self.render_error {
+            Some(error) => html! {
+                <div class="notification is-danger">{ error }</div>
+            },
+            None => html! {},
+        };
+
+        let render_tab = |(index, output): (usize, &RenderedFile)| {
+            html! {
+                <li class=if index == selected { "is-active" } else { "" }>
+                    <a onclick=self.link.callback(move |_| Msg::SelectOutput(index))>
+                        { &output.file_name }
+                    </a>
+                </li>
+            }
+        };
+        let tabs = if outputs.len() > 1 {
+            html! {
+                <div class="tabs">
+                    <ul>{ for outputs.iter().enumerate().map(render_tab) }</ul>
+                </div>
+            }
+        } else {
+            html! {}
+        };
+
+        html! {
+            <>
+                <div class="box">
+                    <div class="level">
+                        <div class="level-left">
+                            <h1 class="title">{ "Rendered template" }</h1>
+                        </div>
+                        <div class="level-right">
+                            <label class="checkbox level-item">
+                                <input
+                                    type="checkbox"
+                                    checked=self.is_strict()
+                                    onclick=self.link.callback(|_| Msg::ToggleStrictMode) />
+                                { " Strict mode" }
+                            </label>
+                            <label class="checkbox level-item">
+                                <input
+                                    type="checkbox"
+                                    checked=self.diff_mode
+                                    onclick=self.link.callback(|_| Msg::ToggleDiffMode) />
+                                { " Diff" }
+                            </label>
+                            <label class="checkbox level-item">
+                                <input
+                                    type="checkbox"
+                                    checked=self.tracing
+                                    onclick=self.link.callback(|_| Msg::ToggleTracing) />
+                                { " Source map" }
+                            </label>
+                            <button
+                                class="button is-small level-item"
+                                onclick=self.link.callback(|_| Msg::DownloadOutputs)>
+                                { "Download .zip" }
+                            </button>
+                        </div>
+                    </div>
+                    { timed_out }
+                    { render_error }
+                    { tabs }
+                    { format_status }
+                    { rendered }
+                </div>
+                { self.render_tests(scenario) }
+                { self.render_batch_panel() }
+                <div class="box">
+                    <h1 class="title">{ "Data" }</h1>
+                    <pre>{ format!("{:#}", inputs_data) }</pre>
+                </div>
+            </>
+        }
+    }
+
+    /// Shows the outputs rendered for each row of the imported CSV file.
+    fn render_batch_panel(&self) -> Html {
+        let rows = match &self.batch {
+            Some(rows) => rows,
+            None => return html! {},
+        };
+        let invalid = rows.iter().filter(|row| row.outputs.is_err()).count();
+        let failed = rows.iter().filter(|row| !row.succeeded()).count() - invalid;
+
+        html! {
+            <div class="box">
+                <div class="level">
+                    <div class="level-left">
+                        <h1 class="title">{ "Batch" }</h1>
+                    </div>
+                    <div class="level-right">
+                        <button
+                            class="button is-small level-item"
+                            onclick=self.link.callback(|_| Msg::DownloadBatch)>
+                            { "Download .zip" }
+                        </button>
+                        <button
+                            class="button is-small level-item"
+                            onclick=self.link.callback(|_| Msg::CloseBatch)>
+                            { "Close" }
+                        </button>
+                    </div>
+                </div>
+                <p>
+                    { format!(
+                        "{} row(s): {} invalid, {} failed to render.",
+                        rows.len(),
+                        invalid,
+                        failed
+                    ) }
+                </p>
+                <table class="table is-fullwidth is-narrow">
+                    <thead>
+                        <tr>
+                            <th>{ "Row" }</th>
+                            <th>{ "Status" }</th>
+                            <th>{ "Outputs" }</th>
+                            <th></th>
+                        </tr>
+                    </thead>
+                    <tbody>
+                        { for rows.iter().enumerate().map(|row| render_batch_row(row, &self.link)) }
+                    </tbody>
+                </table>
+            </div>
+        }
+    }
+
+    /// Shows the results of the scenario's tests, if it has any.
+    fn render_tests(&self, scenario: &Scenario) -> Html {
+        if scenario.tests.is_empty() {
+            return html! {};
+        }
+        let results = match &self.test_reports {
+            Some(reports) => {
+                let passed = reports.iter().filter(|report| report.passed()).count();
+                html! {
+                    <>
+                        <p>{ format!("{}/{} test(s) passed.", passed, reports.len()) }</p>
+                        { for reports.iter().map(render_test_report) }
+                    </>
+                }
+            }
+            None => html! {
+                <p>{ format!("{} test(s) not run yet.", scenario.tests.len()) }</p>
+            },
+        };
+
+        html! {
+            <div class="box">
+                <div class="level">
+                    <div class="level-left">
+                        <h1 class="title">{ "Tests" }</h1>
+                    </div>
+                    <div class="level-right">
+                        <button
+                            class="button is-small level-item"
+                            onclick=self.link.callback(|_| Msg::RunTests)>
+                            { "Run tests" }
+                        </button>
+                    </div>
+                </div>
+                { results }
+            </div>
+        }
+    }
+}
+
+fn download_zip(file_name: &str, files: &[(String, &str)]) -> Result<()> {
+    let archive = zip_files(
+        files
+            .iter()
+            .map(|(path, content)| (path.as_str(), content.as_bytes())),
+    )?;
+    download_file(file_name, "application/zip", &archive);
+    Ok(())
+}
+
+fn render_batch_row((index, row): (usize, &RenderedRow), link: &ComponentLink<App>) -> Html {
+    let (class, status) = match &row.outputs {
+        Err(_) => ("tag is-danger", "Invalid"),
+        Ok(_) if row.succeeded() => ("tag is-success", "Rendered"),
+        Ok(_) => ("tag is-warning", "Failed"),
+    };
+    let details: Vec<Html> = match &row.outputs {
+        Ok(outputs) => outputs
+            .iter()
+            .map(|output| match &output.content {
+                Ok(_) => html! { <li>{ &output.file_name }</li> },
+                Err(e) => html! {
+                    <li class="has-text-danger">{ format!("{}: {:#}", output.file_name, e) }</li>
+                },
+            })
+            .collect(),
+        Err(errors) => errors
+            .iter()
+            .map(|error| html! { <li class="has-text-danger">{ error }</li> })
+            .collect(),
+    };
+    let download = if row.files().is_empty() {
+        html! {}
+    } else {
+        html! {
+            <button
+                class="button is-small"
+                onclick=link.callback(move |_| Msg::DownloadBatchRow(index))>
+                { "Download" }
+            </button>
+        }
+    };
+
+    html! {
+        <tr>
+            <td>{ row.row }</td>
+            <td><span class=class>{ status }</span></td>
+            <td><ul>{ for details }</ul></td>
+            <td>
+                <div class="buttons">
+                    <button
+                        class="button is-small"
+                        onclick=link.callback(move |_| Msg::EditBatchRow(index))>
+                        { "Edit" }
+                    </button>
+                    { download }
+                </div>
+            </td>
+        </tr>
+    }
+}
+
+fn render_test_report(report: &TestReport) -> Html {
+    let (class, status) = if report.passed() {
+        ("tag is-success", "Passed")
+    } else {
+        ("tag is-danger", "Failed")
+    };
+    html! {
+        <div class="test-report">
+            <p><span class=class>{ status }</span>{ " " }{ &report.name }</p>
+            { for report.failures.iter().map(render_output_mismatch) }
+        </div>
+    }
+}
+
+/// Shows the diff between the expected and the rendered output, or why it
+/// failed to render.
+fn render_output_mismatch(mismatch: &OutputMismatch) -> Html {
+    match &mismatch.actual {
+        Ok(actual) => html! {
+            <>
+                <p>{ format!("'{}' differs (-expected +actual):", mismatch.template) }</p>
+                { render_diff(&diff_lines(&mismatch.expected, actual)) }
+            </>
+        },
+        Err(message) => html! {
+            <>
+                <p>{ format!("'{}' failed to render:", mismatch.template) }</p>
+                <pre class="has-text-danger">{ message }</pre>
+            </>
+        },
+    }
+}
+
+/// Describes compile errors, with the excerpt of the template where they
+/// are if it is known.
+fn describe_compile_errors(scenario: &Scenario, errors: &[CompileError]) -> Vec<String> {
+    errors
+        .iter()
+        .map(|error| {
+            let excerpt = match error {
+                CompileError::Template(error) => error
+                    .template
+                    .as_ref()
+                    .and_then(|name| template_source(scenario, name))
+                    .and_then(|source| error.excerpt(&source)),
+                CompileError::ScriptHelper { .. } => None,
+            };
+            match excerpt {
+                Some(excerpt) => format!("{}\n\n{}", error, excerpt),
+                None => error.to_string(),
+            }
+        })
+        .collect()
+}
+
+/// Shows a render error, with the excerpt of the template where it happened
+/// if it is known.
+fn render_template_error(scenario: &Scenario, failure: &RenderFailure) -> Html {
+    let excerpt = failure.error.as_ref().and_then(|template_error| {
+        let source = template_source(scenario, template_error.template.as_ref()?)?;
+        template_error.excerpt(&source)
+    });
+    html! {
+        <pre class="has-text-danger">
+            { &failure.message }
+            { excerpt.map(|excerpt| format!("\n\n{}", excerpt)).unwrap_or_default() }
+        </pre>
+    }
+}
+
+/// Shows the lines of the templates which wrote a line of an output, and the
+/// data they read.
+fn render_line_sources(scenario: &Scenario, line: usize, sources: &LineSources) -> Html {
+    let template_lines: Vec<String> = sources
+        .template_lines
+        .iter()
+        .map(|template_line| {
+            let content = template_source(scenario, &template_line.template)
+                .and_then(|source| {
+                    source
+                        .lines()
+                        .nth(template_line.line.saturating_sub(1))
+                        .map(str::to_owned)
+                })
+                .unwrap_or_default();
+            format!(
+                "{}:{} | {}",
+                template_line.template, template_line.line, content
+            )
+        })
+        .collect();
+    let reads: Vec<String> = sources.reads.iter().map(ToString::to_string).collect();
+    html! {
+        <div class="notification line-sources">
+            <p>{ format!("Line {} comes from:", line + 1) }</p>
+            <pre>{ template_lines.join("\n") }</pre>
+            <p>
+                { if reads.is_empty() {
+                    "It reads no inputs.".to_owned()
+                } else {
+                    format!("It reads {}.", reads.join(", "))
+                } }
+            </p>
+        </div>
+    }
+}
+
+/// Shows whether an output parses in its declared format, with the excerpt
+/// of the output where it doesn't.
+fn render_format_status(format: Format, error: Option<&FormatError>, content: &str) -> Html {
+    match error {
+        None => html! {
+            <span class="tag is-success">{ format!("Valid {}", format.name()) }</span>
+        },
+        Some(error) => {
+            let excerpt = error.excerpt(content);
+            html! {
+                <>
+                    <span class="tag is-danger">{ format!("Invalid {}", format.name()) }</span>
+                    <pre class="has-text-danger">
+                        { error }
+                        { excerpt.map(|excerpt| format!("\n\n{}", excerpt)).unwrap_or_default() }
+                    </pre>
+                </>
+            }
+        }
+    }
+}
+
+/// Shows the title of the scenario and what else it tells about itself.
+fn render_metadata(scenario: &Scenario) -> Html {
+    let metadata = &scenario.metadata;
+    if metadata.is_empty() {
+        return html! {};
+    }
+    let version = match &metadata.version {
+        Some(version) => html! { <span class="tag">{ format!("v{}", version) }</span> },
+        None => html! {},
+    };
+    let authors = if metadata.authors.is_empty() {
+        html! {}
+    } else {
+        html! { <p class="subtitle is-6">{ format!("By {}", metadata.authors.join(", ")) }</p> }
+    };
+    let description = match &metadata.description {
+        Some(description) => html! { <p>{ description }</p> },
+        None => html! {},
+    };
+    // Other URLs are shown as text, they could eg. run a script.
+    let homepage = match (metadata.homepage_url(), &metadata.homepage) {
+        (Some(url), _) => html! {
+            <p><a href=url.to_owned() target="_blank" rel="noopener">{ url }</a></p>
+        },
+        (None, Some(homepage)) => html! { <p>{ homepage }</p> },
+        (None, None) => html! {},
+    };
+
+    html! {
+        <div class="box scenario-info">
+            <h1 class="title">{ scenario.title() }{ " " }{ version }</h1>
+            { authors }
+            { description }
+            <div class="tags">
+                { for metadata.tags.iter().map(|tag| html! { <span class="tag is-info is-light">{ tag }</span> }) }
+            </div>
+            { homepage }
+        </div>
+    }
 }
 
 fn render_inputs(
     inputs: &[InputTypes],
     inputs_data: &InputsData,
+    edited: &[Path],
     link: &ComponentLink<App>,
 ) -> Html {
     use crate::views::RenderableInput;
@@ -305,26 +1521,66 @@ fn render_inputs(
     html! {
         <div class="box">
             <h1 class="title">{ "Inputs" }</h1>
-            { for inputs.iter().map(|input| input.render(&Path::default(), inputs_data, link)) }
+            { for inputs.iter().map(|input| input.render(&Path::default(), inputs_data, edited, link)) }
         </div>
     }
 }
 
-fn render_code_column<T: TemplateEngine>(inputs_data: &InputsData, template_engine: &T) -> Html {
-    let rendered = template_engine
-        .render(inputs_data)
-        .unwrap_or_else(|e| e.context("Failed to render the data").to_string());
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
 
-    html! {
-        <>
-            <div class="box">
-                <h1 class="title">{ "Rendered template" }</h1>
-                <pre>{rendered}</pre>
-            </div>
-            <div class="box">
-                <h1 class="title">{ "Data" }</h1>
-                <pre>{ format!("{:#}", inputs_data) }</pre>
-            </div>
-        </>
+    fn restore_fixture(session: &str) -> (Box<Scenario>, JsonValue) {
+        match restore_state(serde_json::from_str(session).unwrap()).unwrap() {
+            State::Loaded {
+                scenario,
+                inputs_data,
+            } => (scenario, inputs_data.into()),
+            State::Init => panic!("The session isn't restored."),
+        }
+    }
+
+    #[test]
+    fn restore_sessions() {
+        let (scenario, inputs_data) =
+            restore_fixture(include_str!("migrations/fixtures/session_v1.json"));
+        assert_eq!(
+            scenario.templates[0].template.source(),
+            "Hello {{name}}!\n{{#each persons}}{{age}} {{/each}}"
+        );
+        assert_eq!(scenario.inputs.len(), 3);
+        assert_eq!(
+            inputs_data,
+            json!({"name": "Bob", "author": {"active": true}, "persons": [{"age": 42}]})
+        );
+
+        let (scenario, inputs_data) =
+            restore_fixture(include_str!("migrations/fixtures/session_v2.json"));
+        assert_eq!(scenario.title(), "Greetings");
+        assert_eq!(inputs_data, json!({"name": "Bob"}));
+
+        let saved = serde_json::to_value(SavedSession {
+            format_version: SESSION.current_version(),
+            state: State::Init,
+        })
+        .unwrap();
+        assert!(matches!(restore_state(saved), Ok(State::Init)));
+    }
+
+    #[test]
+    fn restore_newer_sessions() {
+        let newer = json!({"format_version": 3, "state": "Init"});
+        assert!(restore_state(newer).unwrap_err().is::<UnsupportedVersion>());
+
+        // Only the format of the scenario changed.
+        let mut newer: JsonValue =
+            serde_json::from_str(include_str!("migrations/fixtures/session_v2.json")).unwrap();
+        newer["state"]["Loaded"]["scenario"]["format_version"] = json!(3);
+        let error = restore_state(newer).unwrap_err();
+        assert!(error.is::<UnsupportedVersion>());
+        assert!(error
+            .to_string()
+            .starts_with("This scenario has the format version 3, "));
     }
 }
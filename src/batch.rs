@@ -0,0 +1,399 @@
+//! Mail-merge: rendering the outputs of a scenario once per row of a CSV
+//! file, whose header row gives the path of the input of each column, eg.
+//! `persons.0.first_name`.
+
+use crate::{
+    inputs::{
+        default_data, validate_data, BooleanInput, InputTypes, ListInput, NumberInput,
+        ValidationError,
+    },
+    prelude::*,
+    scenario::Scenario,
+    template_engine::{RenderedOutput, TemplateEngine},
+    InputsData, Path,
+};
+
+/// The data of a row of a CSV file.
+#[derive(Debug)]
+pub struct BatchRow {
+    /// The number of the row in a spreadsheet, where the header is row 1.
+    pub row: usize,
+    pub data: InputsData,
+    /// The cells which don't convert to their input, and the data which
+    /// doesn't validate.
+    pub errors: Vec<ValidationError>,
+}
+
+/// The outputs rendered from a row, unless its data is invalid.
+#[derive(Debug)]
+pub struct RenderedRow {
+    pub row: usize,
+    pub data: InputsData,
+    pub outputs: Result<Vec<RenderedOutput>, Vec<ValidationError>>,
+}
+
+/// Parses a CSV file and builds the data of each of its rows, starting from
+/// the default data of the inputs. Empty cells are skipped, so that rows
+/// can fill lists with fewer items than there are columns for.
+pub fn read_rows(inputs: &[InputTypes], csv: &str) -> Result<Vec<BatchRow>> {
+    let mut records = parse_csv(csv)?.into_iter();
+    let header = records.next().context("The CSV file is empty.")?;
+    let paths = header
+        .iter()
+        .enumerate()
+        .map(|(i, name)| match name.trim() {
+            "" => bail!("The column {} has no header.", i + 1),
+            name => Ok(Path::from(name)),
+        })
+        .collect::<Result<Vec<_>>>()?;
+    if let Some((i, path)) = paths
+        .iter()
+        .enumerate()
+        .find(|(i, path)| paths[..*i].contains(path))
+    {
+        bail!("The column {} duplicates the header '{}'.", i + 1, path);
+    }
+    for (i, path) in paths.iter().enumerate() {
+        let segments: Vec<&str> = path.get_segments().collect();
+        check_list_indices(inputs, &Path::default(), &segments, paths.len())
+            .with_context(|| format!("The column {} ('{}') is invalid", i + 1, path))?;
+    }
+
+    let rows = records
+        .enumerate()
+        .filter(|(_, cells)| cells.iter().any(|cell| !cell.is_empty()))
+        .map(|(i, cells)| {
+            let mut data = default_data(inputs);
+            let mut errors = vec![];
+            if cells.len() > paths.len() {
+                errors.push(ValidationError::new(
+                    &Path::default(),
+                    format!("{} cells for {} columns.", cells.len(), paths.len()),
+                ));
+            }
+            for (path, cell) in paths.iter().zip(&cells) {
+                if cell.is_empty() {
+                    continue;
+                }
+                let input = prepare_path(inputs, &Path::default(), path, &mut data);
+                let inserted =
+                    cell_value(input, cell).and_then(|value| data.insert_at(path, value));
+                if let Err(e) = inserted {
+                    errors.push(ValidationError::new(path, format!("{:#}", e)));
+                }
+            }
+            if errors.is_empty() {
+                errors = validate_data(inputs, &data);
+            }
+            BatchRow {
+                row: i + 2,
+                data,
+                errors,
+            }
+        })
+        .collect();
+    Ok(rows)
+}
+
+/// Renders the outputs of every valid row of a CSV file.
+pub fn render_rows<E: TemplateEngine>(
+    engine: &E,
+    scenario: &Scenario,
+    csv: &str,
+) -> Result<Vec<RenderedRow>> {
+    Ok(read_rows(&scenario.inputs, csv)?
+        .into_iter()
+        .map(|row| RenderedRow {
+            row: row.row,
+            outputs: if row.errors.is_empty() {
+                Ok(engine.render_outputs(scenario, &row.data))
+            } else {
+                Err(row.errors)
+            },
+            data: row.data,
+        })
+        .collect())
+}
+
+impl RenderedRow {
+    /// Returns the outputs which rendered, with their path in the combined
+    /// archive, eg. `row-2/README.md`.
+    pub fn files(&self) -> Vec<(String, &str)> {
+        self.outputs
+            .iter()
+            .flatten()
+            .filter_map(|output| {
+                let content = output.content.as_ref().ok()?;
+                Some((
+                    format!("row-{}/{}", self.row, output.file_name),
+                    content.as_str(),
+                ))
+            })
+            .collect()
+    }
+
+    /// Whether the row is valid and all its outputs rendered.
+    pub fn succeeded(&self) -> bool {
+        match &self.outputs {
+            Ok(outputs) => outputs.iter().all(|output| output.content.is_ok()),
+            Err(_) => false,
+        }
+    }
+}
+
+/// Finds the input that the column `path` fills, whose list indices are
+/// skipped, and makes sure that the lists on the way are arrays in `data`
+/// so that the indices insert items.
+fn prepare_path<'a>(
+    inputs: &'a [InputTypes],
+    base: &Path,
+    path: &Path,
+    data: &mut InputsData,
+) -> Option<&'a InputTypes> {
+    let segments: Vec<&str> = path.get_segments().collect();
+    inputs.iter().find_map(|input| {
+        let key: Vec<&str> = input.key().get_segments().collect();
+        let rest = segments.strip_prefix(key.as_slice())?;
+        let key = base + input.key();
+        if rest.is_empty() {
+            return Some(input);
+        }
+        if input.downcast_ref::<ListInput>().is_some() {
+            if !data.get_at(&key).is_some_and(JsonValue::is_array) {
+                data.insert_at(&key, JsonValue::Array(vec![])).ok()?;
+            }
+            let (index, rest) = rest.split_first()?;
+            let item = &key + &Path::from(*index);
+            prepare_path(input.children(), &item, &Path::from(rest.join(".")), data)
+        } else {
+            prepare_path(input.children(), &key, &Path::from(rest.join(".")), data)
+        }
+    })
+}
+
+/// Checks that the list indices of a column are below `max_items`, or the
+/// `max_length` of their list if it is smaller, since the lists are resized
+/// up to them.
+fn check_list_indices(
+    inputs: &[InputTypes],
+    base: &Path,
+    segments: &[&str],
+    max_items: usize,
+) -> Result<()> {
+    for input in inputs {
+        let key: Vec<&str> = input.key().get_segments().collect();
+        let rest = match segments.strip_prefix(key.as_slice()) {
+            Some(rest) if !rest.is_empty() => rest,
+            _ => continue,
+        };
+        let key = base + input.key();
+        let list = match input.downcast_ref::<ListInput>() {
+            Some(list) => list,
+            None => return check_list_indices(input.children(), &key, rest, max_items),
+        };
+        let (index, rest) = (rest[0], &rest[1..]);
+        let max = list
+            .max_length
+            .map_or(max_items, |max_length| max_items.min(max_length as usize));
+        match index.parse::<usize>() {
+            Ok(i) if i < max => {}
+            Ok(_) => bail!("The list '{}' can have at most {} items.", key, max),
+            Err(_) => return Ok(()),
+        }
+        let item = &key + &Path::from(index);
+        return check_list_indices(input.children(), &item, rest, max_items);
+    }
+    Ok(())
+}
+
+/// Converts a cell to the type of the value of its input, if it has one.
+fn cell_value(input: Option<&InputTypes>, cell: &str) -> Result<JsonValue> {
+    let input = match input {
+        Some(input) => input,
+        None => return Ok(JsonValue::String(cell.to_owned())),
+    };
+    if input.downcast_ref::<NumberInput>().is_some() {
+        let number = cell
+            .trim()
+            .parse::<serde_json::Number>()
+            .map_err(|_| anyhow!("'{}' isn't a number.", cell))?;
+        Ok(JsonValue::Number(number))
+    } else if input.downcast_ref::<BooleanInput>().is_some() {
+        match cell.trim().to_lowercase().as_str() {
+            "true" | "yes" | "1" | "x" => Ok(JsonValue::Bool(true)),
+            "false" | "no" | "0" => Ok(JsonValue::Bool(false)),
+            _ => bail!("'{}' isn't a boolean, use true or false.", cell),
+        }
+    } else {
+        Ok(JsonValue::String(cell.to_owned()))
+    }
+}
+
+/// Parses the records of a CSV file, see RFC 4180. The cells are separated
+/// by commas, or by semicolons if the header has some but no comma, as
+/// exported by some spreadsheets.
+pub fn parse_csv(csv: &str) -> Result<Vec<Vec<String>>> {
+    let csv = csv.trim_start_matches('\u{feff}');
+    let header = csv.lines().next().unwrap_or_default();
+    let separator = if header.contains(';') && !header.contains(',') {
+        ';'
+    } else {
+        ','
+    };
+
+    let mut records = vec![];
+    let mut record = vec![];
+    let mut cell = String::new();
+    let mut chars = csv.chars().peekable();
+    let mut quoted = false;
+    let (mut line, mut quote_line) = (1, 1);
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted => {
+                if chars.peek() == Some(&'"') {
+                    chars.next();
+                    cell.push('"');
+                } else {
+                    quoted = false;
+                }
+            }
+            '"' if cell.is_empty() => {
+                quoted = true;
+                quote_line = line;
+            }
+            '\n' if quoted => {
+                line += 1;
+                cell.push(c);
+            }
+            _ if quoted => cell.push(c),
+            '\r' if chars.peek() == Some(&'\n') => {}
+            '\n' => {
+                line += 1;
+                record.push(std::mem::take(&mut cell));
+                records.push(std::mem::take(&mut record));
+            }
+            _ if c == separator => record.push(std::mem::take(&mut cell)),
+            _ => cell.push(c),
+        }
+    }
+    if quoted {
+        bail!("The quoted cell at line {} isn't closed.", quote_line);
+    }
+    if !cell.is_empty() || !record.is_empty() {
+        record.push(cell);
+        records.push(record);
+    }
+    Ok(records)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::template_engine::ScenarioEngine;
+    use serde_json::json;
+
+    #[test]
+    fn parse_records() {
+        assert_eq!(
+            parse_csv("a,b\r\n\"x, \"\"y\"\"\",\"multi\nline\"\n1,\n").unwrap(),
+            vec![
+                vec!["a", "b"],
+                vec!["x, \"y\"", "multi\nline"],
+                vec!["1", ""]
+            ]
+        );
+        assert_eq!(
+            parse_csv("a;b\n1;2").unwrap(),
+            vec![vec!["a", "b"], vec!["1", "2"]]
+        );
+        assert!(parse_csv("a\n\"b\n").is_err());
+    }
+
+    fn scenario() -> Scenario {
+        serde_json::from_value(json!({
+            "templates": [{
+                "name": "letter",
+                "template": "Dear {{name}},{{#each persons}} {{first_name}} ({{age}}){{/each}}",
+                "output": true,
+                "file_name": "{{name}}.txt"
+            }],
+            "inputs": [
+                {"type": "text", "key": "name", "name": "Name", "required": true},
+                {"type": "boolean", "key": "active", "name": "Active"},
+                {
+                    "type": "list",
+                    "key": "persons",
+                    "name": "Persons",
+                    "inputs": [
+                        {"type": "text", "key": "first_name", "name": "First name"},
+                        {"type": "number", "key": "age", "name": "Age"}
+                    ]
+                }
+            ]
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn rows_data() {
+        let csv = "name,active,persons.0.first_name,persons.0.age,persons.1.first_name,extra\n\
+                   Bob,yes,Ann,7,Tom,x\n\
+                   \n\
+                   ,no,Ann,seven,,\n";
+        let rows = read_rows(&scenario().inputs, csv).unwrap();
+        assert_eq!(rows.len(), 2);
+        assert_eq!(rows[0].row, 2);
+        assert!(rows[0].errors.is_empty());
+        assert_eq!(
+            JsonValue::from(rows[0].data.clone()),
+            json!({
+                "name": "Bob",
+                "active": true,
+                "persons": [{"first_name": "Ann", "age": 7}, {"first_name": "Tom"}],
+                "extra": "x"
+            })
+        );
+        assert_eq!(rows[1].row, 4);
+        assert_eq!(
+            rows[1].errors,
+            vec![ValidationError::new(
+                &Path::from("persons.0.age"),
+                "'seven' isn't a number."
+            )]
+        );
+
+        assert!(read_rows(&scenario().inputs, "name,,age\n").is_err());
+        assert_eq!(
+            format!(
+                "{:#}",
+                read_rows(&scenario().inputs, "name,persons.999999999.age\n").unwrap_err()
+            ),
+            "The column 2 ('persons.999999999.age') is invalid: \
+             The list 'persons' can have at most 2 items."
+        );
+        assert!(read_rows(&scenario().inputs, "name,name\n").is_err());
+    }
+
+    #[test]
+    fn render_batch() {
+        let scenario = scenario();
+        let engine = ScenarioEngine::with_scenario(&scenario).unwrap();
+        let rows = render_rows(
+            &engine,
+            &scenario,
+            "name,persons.0.first_name,persons.0.age\nBob,Ann,7\n,Tom,8\n",
+        )
+        .unwrap();
+        assert!(rows[0].succeeded());
+        assert_eq!(
+            rows[0].files(),
+            vec![("row-2/Bob.txt".to_owned(), "Dear Bob, Ann (7)")]
+        );
+        assert!(!rows[1].succeeded());
+        assert_eq!(
+            rows[1].outputs.as_ref().unwrap_err()[0].key,
+            Path::from("name")
+        );
+        assert!(rows[1].files().is_empty());
+    }
+}
@@ -0,0 +1,11 @@
+use std::io;
+use wasm_live_handlebars::cli;
+
+fn main() {
+    let code = cli::run(
+        std::env::args().skip(1),
+        &mut io::stdout(),
+        &mut io::stderr(),
+    );
+    std::process::exit(code);
+}
@@ -0,0 +1,9 @@
+use wasm_live_handlebars::agents::RenderWorker;
+use yew::agent::Threaded;
+
+fn main() {
+    wasm_live_handlebars::init();
+    yew::initialize();
+    RenderWorker::register();
+    yew::run_loop();
+}
@@ -0,0 +1,333 @@
+//! The headless renderer of the `render` binary, which renders scenarios
+//! without a browser, eg. in CI.
+
+use crate::{
+    inputs::validate_data,
+    prelude::*,
+    scenario::Scenario,
+    template_engine::{ScenarioEngine, TemplateEngine},
+    InputsData,
+};
+use serde::de::DeserializeOwned;
+use std::{
+    convert::TryFrom,
+    fs,
+    io::Write,
+    path::{Component, PathBuf},
+};
+
+pub const USAGE: &str = "\
+Usage: render <scenario> <data> [--out-dir <dir>] [--strict | --no-strict]
+
+Renders the outputs of a scenario from the data of its inputs, both read
+from JSON or YAML files, to the standard output or to files in a directory.
+
+Options:
+    --out-dir <dir>  Writes each output to its file name in <dir>.
+    --strict         Fails when a template reads a missing variable.
+    --no-strict      Renders missing variables as empty strings.";
+
+/// The arguments are invalid, or the files can't be read or written.
+pub const EXIT_USAGE: i32 = 1;
+/// The data doesn't match the inputs of the scenario.
+pub const EXIT_INVALID_DATA: i32 = 2;
+/// The templates don't compile, or an output fails to render or doesn't
+/// parse in its declared format.
+pub const EXIT_RENDER_FAILED: i32 = 3;
+
+#[derive(Debug, PartialEq)]
+struct Options {
+    scenario: PathBuf,
+    data: PathBuf,
+    out_dir: Option<PathBuf>,
+    /// Overrides the strict mode of the scenario.
+    strict: Option<bool>,
+}
+
+/// Why the renderer stopped, reported with its exit code.
+struct Failure {
+    code: i32,
+    message: String,
+}
+
+impl Failure {
+    fn new(code: i32, message: impl fmt::Display) -> Self {
+        Self {
+            code,
+            message: message.to_string(),
+        }
+    }
+}
+
+impl From<anyhow::Error> for Failure {
+    fn from(error: anyhow::Error) -> Self {
+        Failure::new(EXIT_USAGE, format!("{:#}", error))
+    }
+}
+
+/// Runs the renderer with the command-line arguments, without the name of
+/// the program, and returns its exit code.
+pub fn run(
+    args: impl IntoIterator<Item = String>,
+    stdout: &mut dyn Write,
+    stderr: &mut dyn Write,
+) -> i32 {
+    let result = Options::parse(args)
+        .map_err(|e| Failure::new(EXIT_USAGE, format!("{:#}\n\n{}", e, USAGE)))
+        .and_then(|options| render(&options, stdout));
+    match result {
+        Ok(()) => 0,
+        Err(failure) => {
+            let _ = writeln!(stderr, "{}", failure.message);
+            failure.code
+        }
+    }
+}
+
+impl Options {
+    fn parse(args: impl IntoIterator<Item = String>) -> Result<Self> {
+        let mut files = vec![];
+        let mut out_dir = None;
+        let mut strict = None;
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--out-dir" => match args.next() {
+                    Some(dir) => out_dir = Some(PathBuf::from(dir)),
+                    None => bail!("Missing the directory after --out-dir."),
+                },
+                "--strict" => strict = Some(true),
+                "--no-strict" => strict = Some(false),
+                _ if arg.starts_with("--") => bail!("Unknown option: {}.", arg),
+                _ => files.push(PathBuf::from(arg)),
+            }
+        }
+
+        match <[PathBuf; 2]>::try_from(files) {
+            Ok([scenario, data]) => Ok(Options {
+                scenario,
+                data,
+                out_dir,
+                strict,
+            }),
+            Err(_) => bail!("Expected a scenario file and a data file."),
+        }
+    }
+}
+
+fn render(options: &Options, stdout: &mut dyn Write) -> Result<(), Failure> {
+    let scenario: Scenario = read_file(&options.scenario).context("Invalid scenario")?;
+    let data = InputsData::from(read_file::<JsonValue>(&options.data)?);
+
+    let errors = validate_data(&scenario.inputs, &data);
+    if !errors.is_empty() {
+        let errors: Vec<String> = errors.iter().map(ToString::to_string).collect();
+        return Err(Failure::new(
+            EXIT_INVALID_DATA,
+            format!("Invalid data:\n{}", errors.join("\n")),
+        ));
+    }
+
+    let mut engine = ScenarioEngine::with_scenario(&scenario).map_err(|errors| {
+        let errors: Vec<String> = errors.iter().map(ToString::to_string).collect();
+        Failure::new(EXIT_RENDER_FAILED, errors.join("\n"))
+    })?;
+    if let Some(strict) = options.strict {
+        engine.set_strict_mode(strict);
+    }
+    let outputs = engine.render_outputs(&scenario, &JsonValue::from(data));
+
+    let mut files = vec![];
+    let mut errors = vec![];
+    for output in outputs {
+        match (output.content, output.format_error) {
+            (Ok(content), None) => files.push((output.file_name, content)),
+            (Ok(_), Some(error)) => errors.push(format!("{}: {}", output.file_name, error)),
+            (Err(e), _) => errors.push(format!("{}: {:#}", output.file_name, e)),
+        }
+    }
+    if !errors.is_empty() {
+        return Err(Failure::new(EXIT_RENDER_FAILED, errors.join("\n")));
+    }
+
+    match &options.out_dir {
+        Some(dir) => write_files(dir, &files)?,
+        None => print_files(stdout, &files)?,
+    }
+    Ok(())
+}
+
+/// Reads a JSON file, or a YAML one if its extension is `.yaml` or `.yml`.
+fn read_file<T: DeserializeOwned>(path: &std::path::Path) -> Result<T> {
+    let content =
+        fs::read_to_string(path).with_context(|| format!("Failed to read {}", path.display()))?;
+    let is_yaml = path
+        .extension()
+        .is_some_and(|extension| extension == "yaml" || extension == "yml");
+    let parsed = if is_yaml {
+        serde_yaml::from_str(&content).map_err(anyhow::Error::from)
+    } else {
+        serde_json::from_str(&content).map_err(anyhow::Error::from)
+    };
+    parsed.with_context(|| format!("Failed to parse {}", path.display()))
+}
+
+/// Writes the outputs to their file names, which must stay in `dir`.
+fn write_files(dir: &std::path::Path, files: &[(String, String)]) -> Result<()> {
+    for (file_name, content) in files {
+        let relative = std::path::Path::new(file_name);
+        if !relative
+            .components()
+            .all(|component| matches!(component, Component::Normal(_) | Component::CurDir))
+        {
+            bail!(
+                "The output file name '{}' is outside of the directory.",
+                file_name
+            );
+        }
+        let path = dir.join(relative);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)
+                .with_context(|| format!("Failed to create {}", parent.display()))?;
+        }
+        fs::write(&path, content).with_context(|| format!("Failed to write {}", path.display()))?;
+    }
+    Ok(())
+}
+
+/// Prints a single output as is, or every output after its file name.
+fn print_files(stdout: &mut dyn Write, files: &[(String, String)]) -> Result<()> {
+    match files {
+        [(_, content)] => write!(stdout, "{}", content)?,
+        _ => {
+            for (i, (file_name, content)) in files.iter().enumerate() {
+                if i > 0 {
+                    writeln!(stdout)?;
+                }
+                writeln!(stdout, "==> {} <==", file_name)?;
+                write!(stdout, "{}", content)?;
+                if !content.ends_with('\n') {
+                    writeln!(stdout)?;
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    /// Writes the files of a test in a new directory.
+    fn test_dir(name: &str, files: &[(&str, String)]) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("cli-{}-{}", name, std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        for (file_name, content) in files {
+            fs::write(dir.join(file_name), content).unwrap();
+        }
+        dir
+    }
+
+    fn scenario() -> String {
+        json!({
+            "templates": [
+                {"name": "greeting", "template": "Hello {{name}}!\n", "output": true},
+                {"name": "data", "template": "{\"age\": {{age}}}", "output": true,
+                 "file_name": "out/{{name}}.json", "format": "json"}
+            ],
+            "inputs": [
+                {"type": "text", "key": "name", "name": "Name", "required": true},
+                {"type": "number", "key": "age", "name": "Age"}
+            ]
+        })
+        .to_string()
+    }
+
+    /// Runs the renderer in `dir`, returning its exit code and outputs.
+    fn run_in(dir: &std::path::Path, args: &[&str]) -> (i32, String, String) {
+        let args = args.iter().map(|arg| match *arg {
+            "scenario.json" | "data.json" | "data.yaml" | "out" => {
+                dir.join(arg).display().to_string()
+            }
+            _ => arg.to_string(),
+        });
+        let (mut stdout, mut stderr) = (vec![], vec![]);
+        let code = run(args, &mut stdout, &mut stderr);
+        (
+            code,
+            String::from_utf8(stdout).unwrap(),
+            String::from_utf8(stderr).unwrap(),
+        )
+    }
+
+    #[test]
+    fn parse_options() {
+        let args = |args: &[&str]| Options::parse(args.iter().map(|arg| arg.to_string()));
+        assert_eq!(
+            args(&["s.json", "--strict", "d.yml", "--out-dir", "out"]).unwrap(),
+            Options {
+                scenario: "s.json".into(),
+                data: "d.yml".into(),
+                out_dir: Some("out".into()),
+                strict: Some(true),
+            }
+        );
+        assert!(args(&["s.json"]).is_err());
+        assert!(args(&["s.json", "d.json", "--out-dir"]).is_err());
+        assert!(args(&["s.json", "d.json", "--verbose"]).is_err());
+    }
+
+    #[test]
+    fn render_to_stdout_and_files() {
+        let dir = test_dir(
+            "render",
+            &[
+                ("scenario.json", scenario()),
+                ("data.yaml", "name: Bob\nage: 42\n".to_owned()),
+            ],
+        );
+        let (code, stdout, stderr) = run_in(&dir, &["scenario.json", "data.yaml"]);
+        assert_eq!((code, stderr.as_str()), (0, ""));
+        assert_eq!(
+            stdout,
+            "==> greeting <==\nHello Bob!\n\n==> out/Bob.json <==\n{\"age\": 42}\n"
+        );
+
+        let (code, stdout, _) = run_in(&dir, &["scenario.json", "data.yaml", "--out-dir", "out"]);
+        assert_eq!((code, stdout.as_str()), (0, ""));
+        let written = fs::read_to_string(dir.join("out/out/Bob.json")).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(written, "{\"age\": 42}");
+    }
+
+    #[test]
+    fn exit_codes() {
+        let dir = test_dir(
+            "exit-codes",
+            &[
+                ("scenario.json", scenario()),
+                ("data.json", json!({"name": ""}).to_string()),
+            ],
+        );
+        let (code, _, stderr) = run_in(&dir, &["scenario.json", "data.json"]);
+        assert_eq!(code, EXIT_INVALID_DATA);
+        assert!(stderr.starts_with("Invalid data:\n'name': "));
+
+        // A missing age renders invalid JSON.
+        fs::write(dir.join("data.json"), json!({"name": "Bob"}).to_string()).unwrap();
+        let (code, _, stderr) = run_in(&dir, &["scenario.json", "data.json"]);
+        assert_eq!(code, EXIT_RENDER_FAILED);
+        assert!(stderr.starts_with("out/Bob.json: Invalid JSON: "));
+
+        let (code, _, stderr) = run_in(&dir, &["scenario.json", "data.json", "--strict"]);
+        assert_eq!(code, EXIT_RENDER_FAILED);
+        assert!(stderr.starts_with("out/Bob.json: "));
+
+        let (code, _, stderr) = run_in(&dir, &["scenario.json", "missing.json"]);
+        fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(code, EXIT_USAGE);
+        assert!(stderr.starts_with("Failed to read missing.json"));
+    }
+}
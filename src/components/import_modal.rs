@@ -0,0 +1,89 @@
+use crate::components::NeqAssign;
+use yew::prelude::*;
+
+/// A modal dialog prompting for a document to paste, eg. a JSON Schema.
+pub struct ImportModal {
+    link: ComponentLink<Self>,
+    props: Props,
+    text: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Properties)]
+pub struct Props {
+    pub title: String,
+    #[prop_or_default]
+    pub placeholder: String,
+    pub on_import: Callback<String>,
+    pub on_close: Callback<()>,
+}
+
+#[derive(Debug)]
+pub enum Msg {
+    Edited(String),
+    Import,
+    Close,
+}
+
+impl Component for ImportModal {
+    type Properties = Props;
+    type Message = Msg;
+
+    fn create(props: Self::Properties, link: ComponentLink<Self>) -> Self {
+        Self {
+            link,
+            props,
+            text: String::new(),
+        }
+    }
+
+    fn change(&mut self, props: Self::Properties) -> ShouldRender {
+        self.props.neq_assign(props)
+    }
+
+    fn update(&mut self, msg: Self::Message) -> ShouldRender {
+        match msg {
+            Msg::Edited(text) => {
+                self.text = text;
+                false
+            }
+            Msg::Import => {
+                self.props.on_import.emit(self.text.clone());
+                false
+            }
+            Msg::Close => {
+                self.props.on_close.emit(());
+                false
+            }
+        }
+    }
+
+    fn view(&self) -> Html {
+        html! {
+            <div class="modal is-active">
+                <div class="modal-background" onclick=self.link.callback(|_| Msg::Close)></div>
+                <div class="modal-card">
+                    <header class="modal-card-head">
+                        <p class="modal-card-title">{ &self.props.title }</p>
+                        <button class="delete" aria-label="close" onclick=self.link.callback(|_| Msg::Close)></button>
+                    </header>
+                    <section class="modal-card-body">
+                        <textarea
+                            class="textarea is-family-code"
+                            rows="15"
+                            placeholder=&self.props.placeholder
+                            oninput=self.link.callback(|input: InputData| Msg::Edited(input.value))
+                            />
+                    </section>
+                    <footer class="modal-card-foot">
+                        <button class="button is-success" onclick=self.link.callback(|_| Msg::Import)>
+                            { "Import" }
+                        </button>
+                        <button class="button" onclick=self.link.callback(|_| Msg::Close)>
+                            { "Cancel" }
+                        </button>
+                    </footer>
+                </div>
+            </div>
+        }
+    }
+}
@@ -1,7 +1,11 @@
+mod import_modal;
 mod navbar;
 mod not_equal_assign;
 mod notifications;
+mod template_editor;
 
+pub use import_modal::ImportModal;
 pub use navbar::Navbar;
 pub use not_equal_assign::NeqAssign;
 pub use notifications::Notifications;
+pub use template_editor::TemplateEditor;
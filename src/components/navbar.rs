@@ -92,9 +92,21 @@ impl Component for Navbar {
                                 <a class="navbar-item" onclick=self.link.callback(|_| Msg::LoadFromUrl)>
                                     { "Load from URL" }
                                 </a>
+                                <a class="navbar-item" onclick=self.link.callback(|_| Msg::NavEvent(app::NavEvent::CreateFromJsonSchema))>
+                                    { "Create scenario from JSON Schema" }
+                                </a>
+                                <a class="navbar-item" onclick=self.link.callback(|_| Msg::NavEvent(app::NavEvent::CreateFromTemplate))>
+                                    { "Create scenario from Handlebars template" }
+                                </a>
                                 <a class="navbar-item" onclick=self.link.callback(|_| Msg::RestorePreviousScenario)>
                                     { "Restore a previous scenario" }
                                 </a>
+                                <a class="navbar-item" onclick=self.link.callback(|_| Msg::NavEvent(app::NavEvent::RenderCsvBatch))>
+                                    { "Render a batch from CSV" }
+                                </a>
+                                <a class="navbar-item" onclick=self.link.callback(|_| Msg::NavEvent(app::NavEvent::ExportJsonSchema))>
+                                    { "Export inputs as JSON Schema" }
+                                </a>
                                 <a class="navbar-item" onclick=self.link.callback(|_| Msg::NavEvent(app::NavEvent::UnloadScenario))>
                                     { "Unload the workspace" }
                                 </a>
@@ -0,0 +1,113 @@
+use crate::components::NeqAssign;
+use yew::prelude::*;
+
+/// Edits the templates of a scenario, one tab per template.
+pub struct TemplateEditor {
+    link: ComponentLink<Self>,
+    props: Props,
+    selected: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Properties)]
+pub struct Props {
+    /// The names and sources of the templates.
+    pub templates: Vec<(String, String)>,
+    /// The compile errors of the edited templates, if any.
+    #[prop_or_default]
+    pub errors: Vec<String>,
+    /// Called with the index of the edited template and its new source.
+    pub on_edit: Callback<(usize, String)>,
+}
+
+impl TemplateEditor {
+    /// The index of the shown template, within the templates.
+    fn selected(&self) -> usize {
+        self.selected
+            .min(self.props.templates.len().saturating_sub(1))
+    }
+}
+
+#[derive(Debug)]
+pub enum Msg {
+    Select(usize),
+    Edited(String),
+}
+
+impl Component for TemplateEditor {
+    type Properties = Props;
+    type Message = Msg;
+
+    fn create(props: Self::Properties, link: ComponentLink<Self>) -> Self {
+        Self {
+            link,
+            props,
+            selected: 0,
+        }
+    }
+
+    fn change(&mut self, props: Self::Properties) -> ShouldRender {
+        // Another scenario is loaded, rather than a template edited.
+        let names = |props: &Props| -> Vec<String> {
+            props
+                .templates
+                .iter()
+                .map(|(name, _)| name.clone())
+                .collect()
+        };
+        if names(&self.props) != names(&props) {
+            self.selected = 0;
+        }
+        self.props.neq_assign(props)
+    }
+
+    fn update(&mut self, msg: Self::Message) -> ShouldRender {
+        match msg {
+            Msg::Select(index) => {
+                self.selected = index;
+                true
+            }
+            Msg::Edited(source) => {
+                self.props.on_edit.emit((self.selected(), source));
+                false
+            }
+        }
+    }
+
+    fn view(&self) -> Html {
+        let selected = self.selected();
+        let source = self
+            .props
+            .templates
+            .get(selected)
+            .map(|(_, source)| source.as_str())
+            .unwrap_or_default();
+
+        let render_tab = |(index, (name, _)): (usize, &(String, String))| {
+            html! {
+                <li class=if index == selected { "is-active" } else { "" }>
+                    <a onclick=self.link.callback(move |_| Msg::Select(index))>{ name }</a>
+                </li>
+            }
+        };
+        let render_error = |error: &String| {
+            html! { <pre class="has-text-danger">{ error }</pre> }
+        };
+
+        html! {
+            <div class="box">
+                <h1 class="title">{ "Templates" }</h1>
+                <div class="tabs">
+                    <ul>{ for self.props.templates.iter().enumerate().map(render_tab) }</ul>
+                </div>
+                <textarea
+                    class="textarea is-family-code"
+                    rows="15"
+                    spellcheck="false"
+                    value=source
+                    oninput=self.link.callback(|input: InputData| Msg::Edited(input.value))
+                    />
+                { for self.props.errors.iter().map(render_error) }
+            </div>
+        }
+    }
+}
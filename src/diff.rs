@@ -0,0 +1,188 @@
+//! Line diffs between two renders of an output, with Myers' algorithm.
+
+/// Above this number of inserted and removed lines, outputs are considered
+/// completely different, to bound the time and memory used by the diff.
+const MAX_EDITS: usize = 1000;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LineChange {
+    Unchanged,
+    Inserted,
+    Removed,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct DiffLine {
+    pub change: LineChange,
+    pub content: String,
+}
+
+impl DiffLine {
+    fn new(change: LineChange, content: &str) -> Self {
+        Self {
+            change,
+            content: content.to_owned(),
+        }
+    }
+}
+
+/// Lists the lines of `new`, and the lines of `old` removed from it, in
+/// order. Removed lines come before the lines inserted in their place.
+pub fn diff_lines(old: &str, new: &str) -> Vec<DiffLine> {
+    let old: Vec<&str> = old.lines().collect();
+    let new: Vec<&str> = new.lines().collect();
+
+    // Most renders only change a few lines, which only the common prefix
+    // and suffix have to be trimmed to find.
+    let prefix = old.iter().zip(&new).take_while(|(a, b)| a == b).count();
+    let suffix = old[prefix..]
+        .iter()
+        .rev()
+        .zip(new[prefix..].iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+
+    let unchanged = |lines: &[&str]| {
+        lines
+            .iter()
+            .map(|line| DiffLine::new(LineChange::Unchanged, line))
+            .collect::<Vec<_>>()
+    };
+    let mut diff = unchanged(&old[..prefix]);
+    diff.extend(myers(
+        &old[prefix..old.len() - suffix],
+        &new[prefix..new.len() - suffix],
+    ));
+    diff.extend(unchanged(&old[old.len() - suffix..]));
+    diff
+}
+
+/// Finds the shortest edit script between `old` and `new`, see "An O(ND)
+/// Difference Algorithm and Its Variations" by Eugene W. Myers.
+fn myers(old: &[&str], new: &[&str]) -> Vec<DiffLine> {
+    let (n, m) = (old.len() as isize, new.len() as isize);
+    let max = (n + m) as usize;
+    let offset = max as isize;
+    // The furthest `x` reached on each diagonal `k = x - y`.
+    let mut v = vec![0; 2 * max + 2];
+    // The `v` before each step `d`, restricted to the diagonals [-d, d].
+    let mut trace: Vec<Vec<isize>> = vec![];
+
+    'search: for d in 0..=max.min(MAX_EDITS) as isize {
+        trace.push(v[(offset - d) as usize..=(offset + d) as usize].to_vec());
+        for k in (-d..=d).step_by(2) {
+            let index = (k + offset) as usize;
+            let mut x = if k == -d || (k != d && v[index - 1] < v[index + 1]) {
+                v[index + 1]
+            } else {
+                v[index - 1] + 1
+            };
+            let mut y = x - k;
+            while x < n && y < m && old[x as usize] == new[y as usize] {
+                x += 1;
+                y += 1;
+            }
+            v[index] = x;
+            if x >= n && y >= m {
+                break 'search;
+            }
+        }
+        if d as usize == MAX_EDITS {
+            return replacement(old, new);
+        }
+    }
+
+    let mut diff = vec![];
+    let (mut x, mut y) = (n, m);
+    for (d, v) in trace.iter().enumerate().skip(1).rev() {
+        let d = d as isize;
+        let furthest = |k: isize| v[(k + d) as usize];
+        let k = x - y;
+        let previous_k = if k == -d || (k != d && furthest(k - 1) < furthest(k + 1)) {
+            k + 1
+        } else {
+            k - 1
+        };
+        let previous_x = furthest(previous_k);
+        let previous_y = previous_x - previous_k;
+        while x > previous_x && y > previous_y {
+            x -= 1;
+            y -= 1;
+            diff.push(DiffLine::new(LineChange::Unchanged, old[x as usize]));
+        }
+        if x == previous_x {
+            diff.push(DiffLine::new(
+                LineChange::Inserted,
+                new[previous_y as usize],
+            ));
+        } else {
+            diff.push(DiffLine::new(LineChange::Removed, old[previous_x as usize]));
+        }
+        x = previous_x;
+        y = previous_y;
+    }
+    while x > 0 {
+        x -= 1;
+        diff.push(DiffLine::new(LineChange::Unchanged, old[x as usize]));
+    }
+    diff.reverse();
+    diff
+}
+
+fn replacement(old: &[&str], new: &[&str]) -> Vec<DiffLine> {
+    let removed = old
+        .iter()
+        .map(|line| DiffLine::new(LineChange::Removed, line));
+    let inserted = new
+        .iter()
+        .map(|line| DiffLine::new(LineChange::Inserted, line));
+    removed.chain(inserted).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Shows a diff like a unified diff without its header.
+    fn unified(old: &str, new: &str) -> String {
+        diff_lines(old, new)
+            .into_iter()
+            .map(|line| {
+                let marker = match line.change {
+                    LineChange::Unchanged => ' ',
+                    LineChange::Inserted => '+',
+                    LineChange::Removed => '-',
+                };
+                format!("{}{}\n", marker, line.content)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn identical() {
+        assert_eq!(unified("a\nb", "a\nb\n"), " a\n b\n");
+        assert_eq!(unified("", ""), "");
+    }
+
+    #[test]
+    fn edits() {
+        assert_eq!(unified("a\nb\nc", "a\nx\nc"), " a\n-b\n+x\n c\n");
+        assert_eq!(unified("", "a\nb"), "+a\n+b\n");
+        assert_eq!(unified("a\nb", ""), "-a\n-b\n");
+        assert_eq!(
+            unified("a\nb\nc\na\nb\nb\na", "c\nb\na\nb\na\nc"),
+            "-a\n-b\n c\n+b\n a\n b\n-b\n a\n+c\n"
+        );
+    }
+
+    #[test]
+    fn too_many_edits() {
+        let old: String = (0..MAX_EDITS).map(|i| format!("{}\n", i)).collect();
+        let new: String = (0..MAX_EDITS).map(|i| format!("{}!\n", i)).collect();
+        let diff = diff_lines(&old, &new);
+        assert_eq!(diff.len(), 2 * MAX_EDITS);
+        assert!(diff[..MAX_EDITS]
+            .iter()
+            .all(|line| line.change == LineChange::Removed));
+    }
+}
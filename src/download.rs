@@ -0,0 +1,16 @@
+use stdweb::{js, web::TypedArray};
+
+/// Makes the browser download `content` as a file named `file_name`.
+pub fn download_file(file_name: &str, mime_type: &str, content: &[u8]) {
+    let content = TypedArray::<u8>::from(content);
+    js! { @(no_return)
+        const blob = new Blob([@{content}], { type: @{mime_type} });
+        const link = document.createElement("a");
+        link.href = URL.createObjectURL(blob);
+        link.download = @{file_name};
+        document.body.appendChild(link);
+        link.click();
+        document.body.removeChild(link);
+        URL.revokeObjectURL(link.href);
+    }
+}
@@ -0,0 +1,640 @@
+//! Syntax highlighting of the rendered outputs.
+//!
+//! The highlighters are small lexers rather than full parsers: they only
+//! need to colour generated files, and must be fast enough to run after
+//! each keystroke.
+
+use serde::{Deserialize, Serialize};
+
+/// The language of an output, declared by its template or guessed from
+/// the extension of its file name.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum Language {
+    Rust,
+    Toml,
+    Yaml,
+    Json,
+    Markdown,
+    #[serde(alias = "bash")]
+    Sh,
+    Html,
+}
+
+impl Language {
+    pub fn from_file_name(file_name: &str) -> Option<Self> {
+        let (_, extension) = file_name.rsplit_once('.')?;
+        Some(match extension.to_ascii_lowercase().as_str() {
+            "rs" => Language::Rust,
+            "toml" => Language::Toml,
+            "yaml" | "yml" => Language::Yaml,
+            "json" => Language::Json,
+            "md" | "markdown" => Language::Markdown,
+            "sh" | "bash" => Language::Sh,
+            "html" | "htm" => Language::Html,
+            _ => return None,
+        })
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub enum TokenKind {
+    Comment,
+    Keyword,
+    String,
+    Number,
+    /// Keys of JSON, YAML and TOML documents.
+    Key,
+    /// Types of Rust, ie. capitalized identifiers.
+    Type,
+    /// Rust attributes and HTML attribute names.
+    Attribute,
+    /// Shell variables.
+    Variable,
+    /// Markdown titles and TOML tables.
+    Heading,
+    Tag,
+}
+
+impl TokenKind {
+    /// The CSS class of the tokens of this kind.
+    pub fn class(self) -> &'static str {
+        match self {
+            TokenKind::Comment => "hl-comment",
+            TokenKind::Keyword => "hl-keyword",
+            TokenKind::String => "hl-string",
+            TokenKind::Number => "hl-number",
+            TokenKind::Key => "hl-key",
+            TokenKind::Type => "hl-type",
+            TokenKind::Attribute => "hl-attribute",
+            TokenKind::Variable => "hl-variable",
+            TokenKind::Heading => "hl-heading",
+            TokenKind::Tag => "hl-tag",
+        }
+    }
+}
+
+/// A highlighted range of bytes of the source. The rest is plain text.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub struct Highlight {
+    pub kind: TokenKind,
+    pub start: usize,
+    pub end: usize,
+}
+
+/// Returns the highlights of `source`, sorted and not overlapping.
+pub fn highlight(language: Language, source: &str) -> Vec<Highlight> {
+    let mut lexer = Lexer::new(source);
+    match language {
+        Language::Markdown => lexer.markdown(),
+        Language::Html => lexer.html(),
+        _ => lexer.code(&Syntax::of(language)),
+    }
+    lexer.highlights
+}
+
+/// The lexical rules of the languages handled by [`Lexer::code`].
+struct Syntax {
+    language: Language,
+    line_comment: Option<&'static str>,
+    block_comment: Option<(&'static str, &'static str)>,
+    quotes: &'static [char],
+    keywords: &'static [&'static str],
+    /// Words and strings followed by this are keys.
+    key_separator: Option<char>,
+}
+
+impl Syntax {
+    fn of(language: Language) -> Self {
+        let syntax = Syntax {
+            language,
+            line_comment: Some("#"),
+            block_comment: None,
+            quotes: &['"', '\''],
+            keywords: &["true", "false"],
+            key_separator: None,
+        };
+        match language {
+            Language::Rust => Syntax {
+                line_comment: Some("//"),
+                block_comment: Some(("/*", "*/")),
+                quotes: &['"', '\''],
+                keywords: RUST_KEYWORDS,
+                ..syntax
+            },
+            Language::Toml => Syntax {
+                key_separator: Some('='),
+                ..syntax
+            },
+            Language::Yaml => Syntax {
+                keywords: &["true", "false", "null", "yes", "no", "on", "off"],
+                key_separator: Some(':'),
+                ..syntax
+            },
+            Language::Json => Syntax {
+                line_comment: None,
+                quotes: &['"'],
+                keywords: &["true", "false", "null"],
+                key_separator: Some(':'),
+                ..syntax
+            },
+            Language::Sh => Syntax {
+                keywords: SH_KEYWORDS,
+                ..syntax
+            },
+            Language::Markdown | Language::Html => syntax,
+        }
+    }
+}
+
+const RUST_KEYWORDS: &[&str] = &[
+    "as", "async", "await", "break", "const", "continue", "crate", "dyn", "else", "enum", "extern",
+    "false", "fn", "for", "if", "impl", "in", "let", "loop", "match", "mod", "move", "mut", "pub",
+    "ref", "return", "self", "Self", "static", "struct", "super", "trait", "true", "type",
+    "unsafe", "use", "where", "while",
+];
+
+const SH_KEYWORDS: &[&str] = &[
+    "if", "then", "else", "elif", "fi", "for", "while", "until", "do", "done", "case", "esac",
+    "in", "function", "return", "local", "export", "set", "unset", "exit",
+];
+
+struct Lexer<'a> {
+    source: &'a str,
+    pos: usize,
+    highlights: Vec<Highlight>,
+}
+
+impl<'a> Lexer<'a> {
+    fn new(source: &'a str) -> Self {
+        Self {
+            source,
+            pos: 0,
+            highlights: vec![],
+        }
+    }
+
+    fn rest(&self) -> &'a str {
+        &self.source[self.pos..]
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.rest().chars().next()
+    }
+
+    fn bump(&mut self) {
+        if let Some(c) = self.peek() {
+            self.pos += c.len_utf8();
+        }
+    }
+
+    fn eat(&mut self, prefix: &str) -> bool {
+        let found = self.rest().starts_with(prefix);
+        if found {
+            self.pos += prefix.len();
+        }
+        found
+    }
+
+    fn eat_while(&mut self, predicate: impl Fn(char) -> bool) {
+        while matches!(self.peek(), Some(c) if predicate(c)) {
+            self.bump();
+        }
+    }
+
+    /// Consumes everything up to and including `end`, or up to the end of
+    /// the source.
+    fn eat_until(&mut self, end: &str) {
+        match self.rest().find(end) {
+            Some(index) => self.pos += index + end.len(),
+            None => self.pos = self.source.len(),
+        }
+    }
+
+    fn eat_line(&mut self) {
+        let length = self.rest().find('\n').unwrap_or_else(|| self.rest().len());
+        self.pos += length;
+    }
+
+    /// Consumes a quoted string, with backslash escapes except between
+    /// single quotes in shell scripts and TOML.
+    fn eat_string(&mut self, quote: char, escapes: bool, multiline: bool) {
+        self.bump();
+        while let Some(c) = self.peek() {
+            if c == '\n' && !multiline {
+                return;
+            }
+            self.bump();
+            if c == quote {
+                return;
+            } else if c == '\\' && escapes {
+                self.bump();
+            }
+        }
+    }
+
+    fn push(&mut self, kind: TokenKind, start: usize) {
+        if start < self.pos {
+            self.highlights.push(Highlight {
+                kind,
+                start,
+                end: self.pos,
+            });
+        }
+    }
+
+    /// Whether only whitespace precedes the current position on its line.
+    fn at_line_start(&self) -> bool {
+        self.source[..self.pos]
+            .rsplit('\n')
+            .next()
+            .unwrap_or("")
+            .trim()
+            .is_empty()
+    }
+
+    fn after_whitespace(&self) -> bool {
+        matches!(
+            self.source[..self.pos].chars().last(),
+            None | Some(' ') | Some('\t') | Some('\n')
+        )
+    }
+
+    /// Whether the next non-blank character on the line is `separator`.
+    fn followed_by(&self, separator: char) -> bool {
+        self.rest()
+            .trim_start_matches([' ', '\t'])
+            .starts_with(separator)
+    }
+
+    fn code(&mut self, syntax: &Syntax) {
+        while let Some(c) = self.peek() {
+            let start = self.pos;
+            if let Some((open, close)) = syntax.block_comment {
+                if self.eat(open) {
+                    self.eat_until(close);
+                    self.push(TokenKind::Comment, start);
+                    continue;
+                }
+            }
+            if let Some(line_comment) = syntax.line_comment {
+                // `#` only starts a comment after a blank, eg. not in `a#b`.
+                if self.rest().starts_with(line_comment)
+                    && (line_comment != "#" || self.after_whitespace())
+                {
+                    self.eat_line();
+                    self.push(TokenKind::Comment, start);
+                    continue;
+                }
+            }
+
+            match syntax.language {
+                Language::Rust
+                    if self.rest().starts_with("#[") || self.rest().starts_with("#![") =>
+                {
+                    self.eat_until("]");
+                    self.push(TokenKind::Attribute, start);
+                    continue;
+                }
+                Language::Rust if c == '\'' && !self.is_char_literal() => {
+                    // A lifetime.
+                    self.bump();
+                    self.eat_while(is_word_char);
+                    continue;
+                }
+                Language::Toml if c == '[' && self.at_line_start() => {
+                    self.eat_line();
+                    self.push(TokenKind::Heading, start);
+                    continue;
+                }
+                Language::Sh if c == '$' => {
+                    self.bump();
+                    if self.peek() == Some('{') {
+                        self.eat_until("}");
+                    } else if matches!(self.peek(), Some(c) if is_word_char(c)) {
+                        self.eat_while(is_word_char);
+                    } else {
+                        self.bump();
+                    }
+                    self.push(TokenKind::Variable, start);
+                    continue;
+                }
+                _ => {}
+            }
+
+            if syntax.quotes.contains(&c) {
+                let escapes =
+                    !(c == '\'' && matches!(syntax.language, Language::Sh | Language::Toml));
+                let multiline = matches!(syntax.language, Language::Rust | Language::Sh);
+                self.eat_string(c, escapes, multiline);
+                let kind = self.key_or(syntax, TokenKind::String);
+                self.push(kind, start);
+            } else if c.is_ascii_digit() {
+                self.eat_number();
+                self.push(TokenKind::Number, start);
+            } else if is_word_char(c) {
+                self.eat_while(|c| {
+                    is_word_char(c) || (c == '-' && syntax.language != Language::Rust)
+                });
+                let word = &self.source[start..self.pos];
+                let kind = if syntax.keywords.contains(&word) {
+                    Some(TokenKind::Keyword)
+                } else if syntax.language == Language::Rust
+                    && word.starts_with(|c: char| c.is_ascii_uppercase())
+                {
+                    Some(TokenKind::Type)
+                } else {
+                    None
+                };
+                let kind = match syntax.key_separator {
+                    Some(separator) if self.followed_by(separator) => Some(TokenKind::Key),
+                    _ => kind,
+                };
+                if let Some(kind) = kind {
+                    self.push(kind, start);
+                }
+            } else {
+                self.bump();
+            }
+        }
+    }
+
+    fn key_or(&self, syntax: &Syntax, kind: TokenKind) -> TokenKind {
+        match syntax.key_separator {
+            Some(separator) if self.followed_by(separator) => TokenKind::Key,
+            _ => kind,
+        }
+    }
+
+    /// Tells a Rust char literal, eg. `'a'` or `'\n'`, from a lifetime.
+    fn is_char_literal(&self) -> bool {
+        let mut chars = self.rest().chars().skip(1);
+        match chars.next() {
+            Some('\\') => true,
+            Some(_) => chars.next() == Some('\''),
+            None => false,
+        }
+    }
+
+    /// Consumes a number, with its fractional part, exponent and suffix,
+    /// but not the `..` of a range.
+    fn eat_number(&mut self) {
+        loop {
+            self.eat_while(|c| c.is_ascii_alphanumeric() || c == '_');
+            let mut next = self.rest().chars();
+            if next.next() == Some('.') && matches!(next.next(), Some(c) if c.is_ascii_digit()) {
+                self.bump();
+            } else {
+                break;
+            }
+        }
+    }
+
+    fn markdown(&mut self) {
+        let mut in_code_block = false;
+        while self.pos < self.source.len() {
+            let start = self.pos;
+            let line = self.rest().split('\n').next().unwrap_or("");
+            let trimmed = line.trim_start();
+
+            if trimmed.starts_with("```") || in_code_block {
+                if trimmed.starts_with("```") {
+                    in_code_block = !in_code_block;
+                }
+                self.eat_line();
+                self.push(TokenKind::String, start);
+            } else if trimmed.starts_with('#') {
+                self.eat_line();
+                self.push(TokenKind::Heading, start);
+            } else {
+                let indent = line.len() - trimmed.len();
+                let marker = trimmed
+                    .split(' ')
+                    .next()
+                    .filter(|marker| {
+                        matches!(*marker, "-" | "*" | "+")
+                            || (marker.ends_with('.')
+                                && marker.len() > 1
+                                && marker[..marker.len() - 1]
+                                    .bytes()
+                                    .all(|b| b.is_ascii_digit()))
+                    })
+                    .filter(|marker| trimmed.len() > marker.len());
+                if let Some(marker) = marker {
+                    self.pos += indent;
+                    let marker_start = self.pos;
+                    self.pos += marker.len();
+                    self.push(TokenKind::Keyword, marker_start);
+                }
+                self.markdown_inline(start + line.len());
+            }
+            self.eat("\n");
+        }
+    }
+
+    /// Highlights the inline code spans of a line.
+    fn markdown_inline(&mut self, line_end: usize) {
+        while self.pos < line_end {
+            let start = self.pos;
+            if self.eat("`") {
+                match self.source[self.pos..line_end].find('`') {
+                    Some(index) => {
+                        self.pos += index + 1;
+                        self.push(TokenKind::String, start);
+                    }
+                    None => self.pos = line_end,
+                }
+            } else {
+                self.bump();
+            }
+        }
+    }
+
+    fn html(&mut self) {
+        while let Some(c) = self.peek() {
+            let start = self.pos;
+            if self.eat("<!--") {
+                self.eat_until("-->");
+                self.push(TokenKind::Comment, start);
+            } else if c == '<'
+                && matches!(self.rest()[1..].chars().next(), Some(c) if c.is_ascii_alphabetic() || c == '/' || c == '!')
+            {
+                self.bump();
+                self.eat("/");
+                self.eat("!");
+                self.eat_while(|c| c.is_ascii_alphanumeric() || c == '-');
+                self.push(TokenKind::Tag, start);
+                self.html_attributes();
+            } else {
+                self.bump();
+            }
+        }
+    }
+
+    /// Highlights the attributes of a tag, and its end.
+    fn html_attributes(&mut self) {
+        while let Some(c) = self.peek() {
+            let start = self.pos;
+            if c == '>' || self.rest().starts_with("/>") {
+                self.eat("/");
+                self.bump();
+                self.push(TokenKind::Tag, start);
+                return;
+            } else if c == '"' || c == '\'' {
+                self.eat_string(c, false, true);
+                self.push(TokenKind::String, start);
+            } else if c.is_ascii_alphabetic() {
+                self.eat_while(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | ':'));
+                self.push(TokenKind::Attribute, start);
+            } else {
+                self.bump();
+            }
+        }
+    }
+}
+
+fn is_word_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Lists the highlighted tokens with their kind.
+    fn tokens(language: Language, source: &str) -> Vec<(TokenKind, &str)> {
+        highlight(language, source)
+            .into_iter()
+            .map(|highlight| (highlight.kind, &source[highlight.start..highlight.end]))
+            .collect()
+    }
+
+    #[test]
+    fn languages_from_file_names() {
+        assert_eq!(Language::from_file_name("src/lib.rs"), Some(Language::Rust));
+        assert_eq!(Language::from_file_name("ci.YML"), Some(Language::Yaml));
+        assert_eq!(Language::from_file_name("Makefile"), None);
+        assert_eq!(Language::from_file_name("a.txt"), None);
+    }
+
+    #[test]
+    fn rust() {
+        use TokenKind::*;
+        assert_eq!(
+            tokens(
+                Language::Rust,
+                "#[derive(Debug)]\nfn f<'a>(x: &'a str) -> Option<char> {\n    \
+                 // \"not a string\"\n    let c = '\\n'; /* é */ 1..2.5e3\n}"
+            ),
+            vec![
+                (Attribute, "#[derive(Debug)]"),
+                (Keyword, "fn"),
+                (Type, "Option"),
+                (Comment, "// \"not a string\""),
+                (Keyword, "let"),
+                (String, "'\\n'"),
+                (Comment, "/* é */"),
+                (Number, "1"),
+                (Number, "2.5e3"),
+            ]
+        );
+    }
+
+    #[test]
+    fn data_formats() {
+        use TokenKind::*;
+        assert_eq!(
+            tokens(
+                Language::Toml,
+                "[package]\nname = \"demo\" # comment\nedition = 'x\\'\nsemver-compat = true"
+            ),
+            vec![
+                (Heading, "[package]"),
+                (Key, "name"),
+                (String, "\"demo\""),
+                (Comment, "# comment"),
+                (Key, "edition"),
+                (String, "'x\\'"),
+                (Key, "semver-compat"),
+                (Keyword, "true"),
+            ]
+        );
+        assert_eq!(
+            tokens(Language::Json, "{\"a\" : [1.5, \"b:\", null]}"),
+            vec![
+                (Key, "\"a\""),
+                (Number, "1.5"),
+                (String, "\"b:\""),
+                (Keyword, "null"),
+            ]
+        );
+        assert_eq!(
+            tokens(Language::Yaml, "jobs:\n  - run: a#b # c\n    if: yes"),
+            vec![
+                (Key, "jobs"),
+                (Key, "run"),
+                (Comment, "# c"),
+                (Key, "if"),
+                (Keyword, "yes"),
+            ]
+        );
+    }
+
+    #[test]
+    fn sh() {
+        use TokenKind::*;
+        assert_eq!(
+            tokens(
+                Language::Sh,
+                "if [ -n \"$A\" ]; then echo ${B:-x} $1 'y'; fi"
+            ),
+            vec![
+                (Keyword, "if"),
+                (String, "\"$A\""),
+                (Keyword, "then"),
+                (Variable, "${B:-x}"),
+                (Variable, "$1"),
+                (String, "'y'"),
+                (Keyword, "fi"),
+            ]
+        );
+    }
+
+    #[test]
+    fn markdown_and_html() {
+        use TokenKind::*;
+        assert_eq!(
+            tokens(
+                Language::Markdown,
+                "# Title\n- item `code`\n```sh\n# not a title\n```\n2. b"
+            ),
+            vec![
+                (Heading, "# Title"),
+                (Keyword, "-"),
+                (String, "`code`"),
+                (String, "```sh"),
+                (String, "# not a title"),
+                (String, "```"),
+                (Keyword, "2."),
+            ]
+        );
+        assert_eq!(
+            tokens(
+                Language::Html,
+                "<!-- c --><a href=\"x\" hidden>1 < 2</a><br/>"
+            ),
+            vec![
+                (Comment, "<!-- c -->"),
+                (Tag, "<a"),
+                (Attribute, "href"),
+                (String, "\"x\""),
+                (Attribute, "hidden"),
+                (Tag, ">"),
+                (Tag, "</a"),
+                (Tag, ">"),
+                (Tag, "<br"),
+                (Tag, "/>"),
+            ]
+        );
+    }
+}
@@ -1,6 +1,7 @@
-use super::InputInfo;
-use crate::impl_input_for;
+use super::{Input, InputInfo, ValidationError};
+use crate::{impl_input_boilerplate, InputsData, Path};
 use serde::{Deserialize, Serialize};
+use serde_json::Value as JsonValue;
 
 #[derive(Serialize, Deserialize, Debug)]
 pub struct BooleanInput {
@@ -8,4 +9,18 @@ pub struct BooleanInput {
     pub info: InputInfo,
 }
 
-impl_input_for!(BooleanInput);
+impl Input for BooleanInput {
+    impl_input_boilerplate!("boolean");
+
+    fn default_value(&self) -> JsonValue {
+        JsonValue::Bool(false)
+    }
+
+    fn validate(&self, key_base: &Path, inputs_data: &InputsData) -> Vec<ValidationError> {
+        let key = key_base + self.key();
+        match inputs_data.get_at(&key) {
+            None | Some(JsonValue::Null) | Some(JsonValue::Bool(_)) => vec![],
+            Some(_) => vec![ValidationError::new(&key, "Expected a boolean.")],
+        }
+    }
+}
@@ -1,6 +1,7 @@
-use super::{InputInfo, InputTypes};
-use crate::impl_input_for;
+use super::{Input, InputInfo, InputTypes, ValidationError};
+use crate::{impl_input_boilerplate, InputsData, Path};
 use serde::{Deserialize, Serialize};
+use serde_json::Value as JsonValue;
 
 #[derive(Serialize, Deserialize, Debug)]
 pub struct GroupInput {
@@ -11,4 +12,29 @@ pub struct GroupInput {
     pub show_disable_toggle: bool,
 }
 
-impl_input_for!(GroupInput);
+impl Input for GroupInput {
+    impl_input_boilerplate!("group");
+
+    fn default_value(&self) -> JsonValue {
+        super::default_data(&self.inputs).into()
+    }
+
+    fn validate(&self, key_base: &Path, inputs_data: &InputsData) -> Vec<ValidationError> {
+        let key = key_base + self.key();
+        match inputs_data.get_at(&key) {
+            None | Some(JsonValue::Null) if self.required() => {
+                vec![ValidationError::new(&key, "This group is required.")]
+            }
+            None | Some(JsonValue::Null) | Some(JsonValue::Object(_)) => self
+                .inputs
+                .iter()
+                .flat_map(|input| input.validate(&key, inputs_data))
+                .collect(),
+            Some(_) => vec![ValidationError::new(&key, "Expected an object.")],
+        }
+    }
+
+    fn children(&self) -> &[InputTypes] {
+        &self.inputs
+    }
+}
@@ -1,8 +1,16 @@
-use super::{InputInfo, InputTypes};
-use crate::impl_input_for;
+use super::{Input, InputInfo, InputTypes, ValidationError};
+use crate::{impl_input_boilerplate, InputsData, Path};
+use anyhow::{bail, Result};
 use serde::{Deserialize, Serialize};
+use serde_json::Value as JsonValue;
+use std::convert::TryFrom;
+
+/// The largest `min_length` of lists, whose elements are all created with
+/// the default data.
+pub const MAX_MIN_LENGTH: u64 = 1000;
 
 #[derive(Serialize, Deserialize, Debug)]
+#[serde(try_from = "ListDefinition")]
 pub struct ListInput {
     #[serde(flatten)]
     pub info: InputInfo,
@@ -13,4 +21,93 @@ pub struct ListInput {
     pub max_length: Option<u64>,
 }
 
-impl_input_for!(ListInput);
+/// The definition of a [`ListInput`] as written in scenarios, before its
+/// lengths are checked.
+#[derive(Deserialize)]
+struct ListDefinition {
+    #[serde(flatten)]
+    info: InputInfo,
+    inputs: Vec<InputTypes>,
+    min_length: Option<u64>,
+    max_length: Option<u64>,
+}
+
+impl TryFrom<ListDefinition> for ListInput {
+    type Error = anyhow::Error;
+
+    fn try_from(definition: ListDefinition) -> Result<Self> {
+        let list = ListInput {
+            info: definition.info,
+            inputs: definition.inputs,
+            min_length: definition.min_length,
+            max_length: definition.max_length,
+        };
+        list.check_lengths()?;
+        Ok(list)
+    }
+}
+
+impl ListInput {
+    /// Checks that the `min_length` is reasonable and at most the
+    /// `max_length`.
+    pub fn check_lengths(&self) -> Result<()> {
+        match (self.min_length, self.max_length) {
+            (Some(min), _) if min > MAX_MIN_LENGTH => bail!(
+                "The min_length of a list must be at most {}, not {}.",
+                MAX_MIN_LENGTH,
+                min
+            ),
+            (Some(min), Some(max)) if min > max => bail!(
+                "The min_length of a list ({}) can't be greater than its max_length ({}).",
+                min,
+                max
+            ),
+            _ => Ok(()),
+        }
+    }
+}
+
+impl Input for ListInput {
+    impl_input_boilerplate!("list");
+
+    fn default_value(&self) -> JsonValue {
+        let element = JsonValue::from(super::default_data(&self.inputs));
+        JsonValue::Array(vec![element; self.min_length.unwrap_or(0) as usize])
+    }
+
+    fn validate(&self, key_base: &Path, inputs_data: &InputsData) -> Vec<ValidationError> {
+        let key = key_base + self.key();
+        let len = match inputs_data.get_at(&key) {
+            None | Some(JsonValue::Null) => 0,
+            Some(JsonValue::Array(elements)) => elements.len(),
+            Some(_) => return vec![ValidationError::new(&key, "Expected a list.")],
+        };
+
+        let mut errors = vec![];
+        match (self.min_length, self.max_length) {
+            (Some(min), _) if (len as u64) < min => errors.push(ValidationError::new(
+                &key,
+                format!("Must have at least {} elements.", min),
+            )),
+            (_, Some(max)) if (len as u64) > max => errors.push(ValidationError::new(
+                &key,
+                format!("Must have at most {} elements.", max),
+            )),
+            _ => {}
+        }
+
+        for i in 0..len {
+            let element_key = &key + Path::from(i);
+            errors.extend(
+                self.inputs
+                    .iter()
+                    .flat_map(|input| input.validate(&element_key, inputs_data)),
+            );
+        }
+        errors
+    }
+
+    fn children(&self) -> &[InputTypes] {
+        &self.inputs
+    }
+}
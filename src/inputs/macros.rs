@@ -1,31 +1,30 @@
+/// Implements the methods of [`Input`](crate::inputs::Input) which are the
+/// same for all the input kinds, for a type with an `info: InputInfo` field
+/// and implementing `Serialize`.
+///
+/// ```ignore
+/// impl Input for TextInput {
+///     impl_input_boilerplate!("text");
+///     ...
+/// }
+/// ```
 #[macro_export]
-macro_rules! impl_input_for {
-    ($type:ty) => {
-        impl crate::inputs::Input for $type {
-            fn key(&self) -> &crate::Path {
-                &self.info.key
-            }
+macro_rules! impl_input_boilerplate {
+    ($type_name:expr) => {
+        fn info(&self) -> &$crate::inputs::InputInfo {
+            &self.info
+        }
 
-            fn name(&self) -> &str {
-                &self.info.name
-            }
+        fn type_name(&self) -> &'static str {
+            $type_name
+        }
 
-            fn description(&self) -> Option<&str> {
-                self.info.description.as_deref()
-            }
+        fn definition(&self) -> ::serde_json::Value {
+            $crate::inputs::serialize_definition(self)
         }
-    };
-}
 
-#[macro_export]
-macro_rules! for_all_inputtypes_variants {
-    ($self:expr, $ident:ident => $expr:expr) => {
-        match $self {
-            crate::inputs::InputTypes::Text($ident) => $expr,
-            crate::inputs::InputTypes::Boolean($ident) => $expr,
-            crate::inputs::InputTypes::Number($ident) => $expr,
-            crate::inputs::InputTypes::Group($ident) => $expr,
-            crate::inputs::InputTypes::List($ident) => $expr,
+        fn as_any(&self) -> &dyn std::any::Any {
+            self
         }
     };
 }
@@ -1,29 +1,62 @@
-use crate::{for_all_inputtypes_variants, prelude::*, Path};
+use crate::{prelude::*, InputsData, Path};
+use serde::{Deserializer, Serializer};
+use std::any::Any;
 
 mod boolean;
 mod group;
 mod list;
 mod macros;
 mod number;
+mod registry;
 mod text;
 pub use boolean::*;
 pub use group::*;
 pub use list::*;
 pub use number::*;
+pub use registry::*;
 pub use text::*;
 
-#[derive(Serialize, Deserialize, Debug)]
-#[serde(rename_all = "snake_case")]
-#[serde(tag = "type")]
-pub enum InputTypes {
-    Text(TextInput),
-    Boolean(BooleanInput),
-    Number(NumberInput),
-    Group(GroupInput),
-    /// List differs from groups in that the number of input can
-    /// varry, eg. it can be used to prompt for a list of persons
-    /// of unknown size.
-    List(ListInput),
+/// An input of any registered kind, see [`register_input_kind`].
+///
+/// It is (de)serialized as the definition of the input, with the name of
+/// its kind in the `type` field, eg. `{"type": "text", "key": ...}`.
+#[derive(Debug)]
+pub struct InputTypes(Box<dyn Input>);
+
+impl InputTypes {
+    pub fn new<T: Input + 'static>(input: T) -> Self {
+        Self(Box::new(input))
+    }
+
+    pub fn downcast_ref<T: Input + 'static>(&self) -> Option<&T> {
+        self.0.as_any().downcast_ref()
+    }
+}
+
+impl core::ops::Deref for InputTypes {
+    type Target = dyn Input;
+
+    fn deref(&self) -> &Self::Target {
+        self.0.as_ref()
+    }
+}
+
+impl Serialize for InputTypes {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut definition = serde_json::Map::new();
+        definition.insert("type".into(), self.type_name().into());
+        if let JsonValue::Object(fields) = self.definition() {
+            definition.extend(fields.into_iter().filter(|(field, _)| field != "type"));
+        }
+        definition.serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for InputTypes {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let definition = JsonValue::deserialize(deserializer)?;
+        parse_input(definition).map_err(|e| serde::de::Error::custom(format!("{:#}", e)))
+    }
 }
 
 #[derive(Serialize, Deserialize, Debug)]
@@ -31,26 +64,209 @@ pub struct InputInfo {
     pub key: Path,
     pub name: String,
     pub description: Option<String>,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub required: bool,
 }
 
-pub trait Input {
-    fn key(&self) -> &Path;
+/// The behaviour of an input, implemented by each input kind.
+///
+/// Most of the boilerplate can be implemented with
+/// [`impl_input_boilerplate!`](crate::impl_input_boilerplate). With the
+/// `ui` feature, the form field of a kind is registered separately, see
+/// `views::register_input_view`.
+pub trait Input: fmt::Debug {
+    fn info(&self) -> &InputInfo;
 
-    fn name(&self) -> &str;
+    /// The name of the kind of this input, as registered.
+    fn type_name(&self) -> &'static str;
 
-    fn description(&self) -> Option<&str>;
-}
+    /// Serializes the definition of this input, without its `type`.
+    fn definition(&self) -> JsonValue;
+
+    fn as_any(&self) -> &dyn Any;
+
+    /// The value of this input when nothing has been entered yet.
+    fn default_value(&self) -> JsonValue;
+
+    /// Checks the data entered for this input and its children, if any.
+    fn validate(&self, key_base: &Path, inputs_data: &InputsData) -> Vec<ValidationError>;
+
+    /// The nested inputs, for the kinds that have some.
+    fn children(&self) -> &[InputTypes] {
+        &[]
+    }
 
-impl Input for InputTypes {
     fn key(&self) -> &Path {
-        for_all_inputtypes_variants! { self, i => i.key() }
+        &self.info().key
     }
 
     fn name(&self) -> &str {
-        for_all_inputtypes_variants! { self, i => i.name() }
+        &self.info().name
     }
 
     fn description(&self) -> Option<&str> {
-        for_all_inputtypes_variants! { self, i => i.description() }
+        self.info().description.as_deref()
+    }
+
+    fn required(&self) -> bool {
+        self.info().required
+    }
+}
+
+/// Data entered for an input which doesn't match its definition.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ValidationError {
+    pub key: Path,
+    pub message: String,
+}
+
+impl ValidationError {
+    pub fn new<T: ToString>(key: &Path, message: T) -> Self {
+        Self {
+            key: key.clone(),
+            message: message.to_string(),
+        }
+    }
+}
+
+impl fmt::Display for ValidationError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "'{}': {}", self.key, self.message)
+    }
+}
+
+/// Serializes the definition of an input, used by
+/// [`impl_input_boilerplate!`](crate::impl_input_boilerplate).
+pub fn serialize_definition<T: Serialize>(input: &T) -> JsonValue {
+    serde_json::to_value(input).unwrap_or_else(|e| {
+        error!("Failed to serialize an input: {:?}", e);
+        JsonValue::Null
+    })
+}
+
+/// Returns the data of inputs which haven't been edited yet.
+pub fn default_data(inputs: &[InputTypes]) -> InputsData {
+    let mut data = InputsData::default();
+    for input in inputs {
+        if let Err(e) = data.insert_at(input.key(), input.default_value()) {
+            warn!("Invalid input key '{}': {:?}", input.key(), e);
+        }
+    }
+    data
+}
+
+/// Checks the data entered for all the given inputs.
+pub fn validate_data(inputs: &[InputTypes], inputs_data: &InputsData) -> Vec<ValidationError> {
+    inputs
+        .iter()
+        .flat_map(|input| input.validate(&Path::default(), inputs_data))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn inputs() -> Vec<InputTypes> {
+        serde_json::from_value(json!([
+            {"type": "text", "key": "date", "name": "Date", "validate_regex": "^\\d{4}$"},
+            {"type": "text", "key": "name", "name": "Name", "required": true},
+            {"type": "number", "key": "age", "name": "Age", "min": 0, "max": 150},
+            {"type": "boolean", "key": "active", "name": "Active"},
+            {
+                "type": "list",
+                "key": "persons",
+                "name": "Persons",
+                "min_length": 1,
+                "inputs": [{"type": "number", "key": "age", "name": "Age", "required": true}]
+            }
+        ]))
+        .unwrap()
+    }
+
+    #[test]
+    fn default_data_of_inputs() {
+        assert_eq!(
+            JsonValue::from(default_data(&inputs())),
+            json!({
+                "date": "",
+                "name": "",
+                "age": null,
+                "active": false,
+                "persons": [{"age": null}]
+            })
+        );
+    }
+
+    #[test]
+    fn validate_valid_data() {
+        let data: InputsData = json!({
+            "date": "2020",
+            "name": "Toto",
+            "age": 42,
+            "active": true,
+            "persons": [{"age": 1}, {"age": 2}]
+        })
+        .into();
+        assert_eq!(validate_data(&inputs(), &data), vec![]);
+    }
+
+    #[test]
+    fn validate_invalid_data() {
+        let data: InputsData = json!({
+            "date": "20/20",
+            "age": 151,
+            "active": "yes",
+            "persons": [{"age": 1}, {"age": "old"}, {}]
+        })
+        .into();
+        let errors: Vec<String> = validate_data(&inputs(), &data)
+            .iter()
+            .map(ToString::to_string)
+            .collect();
+        assert_eq!(
+            errors,
+            vec![
+                "'date': '20/20' doesn't match the pattern: ^\\d{4}$",
+                "'name': This field is required.",
+                "'age': Must be less than or equal to 150.",
+                "'active': Expected a boolean.",
+                "'persons.1.age': 'old' is not a number.",
+                "'persons.2.age': This field is required.",
+            ]
+        );
+    }
+    #[test]
+    fn invalid_validate_regex() {
+        let error = serde_json::from_value::<InputTypes>(
+            json!({"type": "text", "key": "date", "name": "Date", "validate_regex": "\\d{4"}),
+        )
+        .unwrap_err();
+        assert!(error
+            .to_string()
+            .contains("Invalid validation regex: regex parse error"));
+    }
+
+    #[test]
+    fn invalid_list_lengths() {
+        let list = |min_length: u64, max_length: u64| {
+            serde_json::from_value::<InputTypes>(json!({
+                "type": "list",
+                "key": "persons",
+                "name": "Persons",
+                "min_length": min_length,
+                "max_length": max_length,
+                "inputs": []
+            }))
+            .map_err(|e| e.to_string())
+        };
+        assert!(list(2, 2).is_ok());
+        assert!(list(3, 2)
+            .unwrap_err()
+            .contains("The min_length of a list (3) can't be greater than its max_length (2)."));
+        assert!(list(u64::MAX, u64::MAX)
+            .unwrap_err()
+            .contains("The min_length of a list must be at most 1000, not 18446744073709551615."));
     }
 }
@@ -1,6 +1,7 @@
-use super::InputInfo;
-use crate::impl_input_for;
+use super::{Input, InputInfo, ValidationError};
+use crate::{impl_input_boilerplate, InputsData, Path};
 use serde::{Deserialize, Serialize};
+use serde_json::Value as JsonValue;
 
 pub type JsonNumber = serde_json::Number;
 
@@ -16,4 +17,41 @@ pub struct NumberInput {
     pub step: Option<JsonNumber>,
 }
 
-impl_input_for!(NumberInput);
+impl Input for NumberInput {
+    impl_input_boilerplate!("number");
+
+    fn default_value(&self) -> JsonValue {
+        JsonValue::Null
+    }
+
+    fn validate(&self, key_base: &Path, inputs_data: &InputsData) -> Vec<ValidationError> {
+        let key = key_base + self.key();
+        let number = match inputs_data.get_at(&key) {
+            None | Some(JsonValue::Null) if self.required() => {
+                return vec![ValidationError::new(&key, "This field is required.")]
+            }
+            None | Some(JsonValue::Null) => return vec![],
+            Some(JsonValue::Number(n)) => n.as_f64().unwrap_or_default(),
+            Some(JsonValue::String(s)) => {
+                return vec![ValidationError::new(
+                    &key,
+                    format!("'{}' is not a number.", s),
+                )]
+            }
+            Some(_) => return vec![ValidationError::new(&key, "Expected a number.")],
+        };
+
+        let as_f64 = |n: &Option<JsonNumber>| n.as_ref().and_then(JsonNumber::as_f64);
+        match (as_f64(&self.min), as_f64(&self.max)) {
+            (Some(min), _) if number < min => vec![ValidationError::new(
+                &key,
+                format!("Must be greater than or equal to {}.", min),
+            )],
+            (_, Some(max)) if number > max => vec![ValidationError::new(
+                &key,
+                format!("Must be less than or equal to {}.", max),
+            )],
+            _ => vec![],
+        }
+    }
+}
@@ -0,0 +1,162 @@
+use super::{BooleanInput, GroupInput, Input, InputTypes, ListInput, NumberInput, TextInput};
+use crate::prelude::*;
+use lazy_static::lazy_static;
+use serde::de::DeserializeOwned;
+use std::{collections::HashMap, marker::PhantomData, sync::RwLock};
+
+/// A kind of input, eg. `text` or `list`, which knows how to parse the
+/// definitions of its inputs.
+///
+/// Register new kinds with [`register_input_kind`].
+pub trait InputKind: Send + Sync {
+    /// The value of the `type` field of the inputs of this kind.
+    fn type_name(&self) -> &'static str;
+
+    /// Parses the definition of an input of this kind.
+    fn parse(&self, definition: JsonValue) -> Result<Box<dyn Input>>;
+}
+
+/// An [`InputKind`] whose inputs are deserialized with serde.
+pub struct SerdeInputKind<T> {
+    type_name: &'static str,
+    _input: PhantomData<fn() -> T>,
+}
+
+impl<T> SerdeInputKind<T> {
+    pub fn new(type_name: &'static str) -> Self {
+        Self {
+            type_name,
+            _input: PhantomData,
+        }
+    }
+}
+
+impl<T: Input + DeserializeOwned + 'static> InputKind for SerdeInputKind<T> {
+    fn type_name(&self) -> &'static str {
+        self.type_name
+    }
+
+    fn parse(&self, definition: JsonValue) -> Result<Box<dyn Input>> {
+        let input: T = serde_json::from_value(definition)
+            .with_context(|| format!("Invalid definition of a '{}' input.", self.type_name))?;
+        Ok(Box::new(input))
+    }
+}
+
+lazy_static! {
+    static ref INPUT_KINDS: RwLock<HashMap<&'static str, Box<dyn InputKind>>> = {
+        let builtins: Vec<Box<dyn InputKind>> = vec![
+            Box::new(SerdeInputKind::<TextInput>::new("text")),
+            Box::new(SerdeInputKind::<BooleanInput>::new("boolean")),
+            Box::new(SerdeInputKind::<NumberInput>::new("number")),
+            Box::new(SerdeInputKind::<GroupInput>::new("group")),
+            Box::new(SerdeInputKind::<ListInput>::new("list")),
+        ];
+        RwLock::new(
+            builtins
+                .into_iter()
+                .map(|kind| (kind.type_name(), kind))
+                .collect(),
+        )
+    };
+}
+
+/// Makes the inputs of the given kind available to scenarios, replacing
+/// any kind previously registered with the same type name.
+pub fn register_input_kind<K: InputKind + 'static>(kind: K) {
+    INPUT_KINDS
+        .write()
+        .expect("The input kinds registry is poisoned.")
+        .insert(kind.type_name(), Box::new(kind));
+}
+
+/// Parses the definition of an input, using the kind named in its `type`.
+pub fn parse_input(definition: JsonValue) -> Result<InputTypes> {
+    let type_name = match definition.get("type") {
+        Some(JsonValue::String(type_name)) => type_name.clone(),
+        Some(_) => bail!("The 'type' of an input must be a string."),
+        None => bail!("Missing input 'type'."),
+    };
+
+    let kinds = INPUT_KINDS
+        .read()
+        .expect("The input kinds registry is poisoned.");
+    let kind = kinds
+        .get(type_name.as_str())
+        .ok_or_else(|| anyhow!("Unknown input type: '{}'.", type_name))?;
+    kind.parse(definition).map(InputTypes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        impl_input_boilerplate,
+        inputs::{InputInfo, ValidationError},
+        InputsData, Path,
+    };
+    use serde::{Deserialize, Serialize};
+    use serde_json::json;
+
+    #[derive(Serialize, Deserialize, Debug)]
+    struct ColorInput {
+        #[serde(flatten)]
+        info: InputInfo,
+        default: String,
+    }
+
+    impl Input for ColorInput {
+        impl_input_boilerplate!("color");
+
+        fn default_value(&self) -> JsonValue {
+            JsonValue::String(self.default.clone())
+        }
+
+        fn validate(&self, _: &Path, _: &InputsData) -> Vec<ValidationError> {
+            vec![]
+        }
+    }
+
+    #[test]
+    fn builtin_kinds_round_trip() {
+        let definition = json!({
+            "type": "group",
+            "key": "author",
+            "name": "Author",
+            "description": null,
+            "show_disable_toggle": false,
+            "inputs": [
+                {"type": "text", "key": "name", "name": "Name", "description": null},
+                {"type": "boolean", "key": "active", "name": "Active", "description": null}
+            ]
+        });
+        let input = parse_input(definition.clone()).unwrap();
+        assert_eq!(input.type_name(), "group");
+        assert_eq!(input.children().len(), 2);
+        assert_eq!(serde_json::to_value(&input).unwrap(), definition);
+    }
+
+    #[test]
+    fn custom_kind() {
+        let definition =
+            json!({"type": "color", "key": "bg", "name": "Background", "default": "#fff"});
+        assert!(parse_input(definition.clone()).is_err());
+
+        register_input_kind(SerdeInputKind::<ColorInput>::new("color"));
+        let input = parse_input(definition).unwrap();
+        assert_eq!(input.downcast_ref::<ColorInput>().unwrap().default, "#fff");
+        assert_eq!(input.default_value(), json!("#fff"));
+    }
+
+    #[test]
+    fn invalid_definitions() {
+        [
+            json!({"key": "a", "name": "A"}),
+            json!({"type": 42, "key": "a", "name": "A"}),
+            json!({"type": "unknown", "key": "a", "name": "A"}),
+            json!({"type": "text", "name": "A"}),
+        ]
+        .iter()
+        .for_each(|definition| assert!(parse_input(definition.clone()).is_err()))
+    }
+}
@@ -1,13 +1,79 @@
-use super::InputInfo;
-use crate::impl_input_for;
-use serde::{Deserialize, Serialize};
+use super::{Input, InputInfo, ValidationError};
+use crate::{impl_input_boilerplate, InputsData, Path};
+use regex::Regex;
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+use serde_json::Value as JsonValue;
 
 #[derive(Serialize, Deserialize, Debug)]
 pub struct TextInput {
     #[serde(flatten)]
     pub info: InputInfo,
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub validate_regex: Option<String>, // TODO: use a regex
+    pub validate_regex: Option<ValidateRegex>,
 }
 
-impl_input_for!(TextInput);
+/// The pattern that texts must match, compiled once when the input is
+/// deserialized so that invalid patterns are rejected with the scenario.
+#[derive(Debug, Clone)]
+pub struct ValidateRegex(Regex);
+
+impl ValidateRegex {
+    pub fn new(pattern: &str) -> Result<Self, regex::Error> {
+        Regex::new(pattern).map(ValidateRegex)
+    }
+
+    pub fn as_str(&self) -> &str {
+        self.0.as_str()
+    }
+}
+
+impl Serialize for ValidateRegex {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.as_str())
+    }
+}
+
+impl<'de> Deserialize<'de> for ValidateRegex {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let pattern = String::deserialize(deserializer)?;
+        ValidateRegex::new(&pattern)
+            .map_err(|e| de::Error::custom(format!("Invalid validation regex: {}", e)))
+    }
+}
+
+impl Input for TextInput {
+    impl_input_boilerplate!("text");
+
+    fn default_value(&self) -> JsonValue {
+        JsonValue::String(String::new())
+    }
+
+    fn validate(&self, key_base: &Path, inputs_data: &InputsData) -> Vec<ValidationError> {
+        let key = key_base + self.key();
+        let text = match inputs_data.get_at(&key) {
+            None | Some(JsonValue::Null) => "",
+            Some(JsonValue::String(s)) => s,
+            Some(JsonValue::Array(_)) | Some(JsonValue::Object(_)) => {
+                return vec![ValidationError::new(&key, "Expected a text.")]
+            }
+            // Other scalars are rendered as text
+            Some(_) => return vec![],
+        };
+
+        if text.is_empty() {
+            return if self.required() {
+                vec![ValidationError::new(&key, "This field is required.")]
+            } else {
+                vec![]
+            };
+        }
+
+        match &self.validate_regex {
+            Some(regex) if !regex.0.is_match(text) => vec![ValidationError::new(
+                &key,
+                format!("'{}' doesn't match the pattern: {}", text, regex.as_str()),
+            )],
+            _ => vec![],
+        }
+    }
+}
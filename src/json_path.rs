@@ -18,7 +18,7 @@ impl Path {
         Path::from(segments.join("."))
     }
 
-    fn get_segments(&self) -> impl Iterator<Item = &str> {
+    pub(crate) fn get_segments(&self) -> impl Iterator<Item = &str> {
         self.path.split('.').filter(|s| !s.is_empty())
     }
 }
@@ -33,6 +33,12 @@ impl core::ops::Add for &Path {
     type Output = Path;
 
     fn add(self, rhs: Self) -> Self::Output {
+        if self.path.is_empty() {
+            return rhs.clone();
+        } else if rhs.path.is_empty() {
+            return self.clone();
+        }
+
         let mut result = Path::empty();
         result.path.push_str(&self.path);
         result.path.push('.');
@@ -74,7 +80,7 @@ impl From<usize> for Path {
 /// Represents the data entered in the inputs on the page.
 ///
 /// Backed by a JSON object.
-#[derive(Serialize, Deserialize, Debug, PartialEq)]
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
 pub struct InputsData(JsonValue);
 
 impl Default for InputsData {
@@ -99,6 +105,12 @@ impl From<JsonValue> for InputsData {
     }
 }
 
+impl From<InputsData> for JsonValue {
+    fn from(data: InputsData) -> Self {
+        data.0
+    }
+}
+
 /// TODO: Create a trait instead
 impl InputsData {
     /// TODO
@@ -0,0 +1,260 @@
+use crate::inputs::*;
+use serde_json::{json, Map, Value as JsonValue};
+
+const JSON_SCHEMA_DIALECT: &str = "https://json-schema.org/draft/2020-12/schema";
+
+/// Generates a JSON Schema (draft 2020-12) describing the `InputsData`
+/// produced by the given inputs.
+pub fn json_schema_from_inputs(inputs: &[InputTypes]) -> JsonValue {
+    let mut schema = object_schema(inputs);
+    schema.insert("$schema".into(), JSON_SCHEMA_DIALECT.into());
+    JsonValue::Object(schema)
+}
+
+fn object_schema(inputs: &[InputTypes]) -> Map<String, JsonValue> {
+    let mut schema = Map::new();
+    schema.insert("type".into(), "object".into());
+    schema.insert("properties".into(), JsonValue::Object(Map::new()));
+    for input in inputs {
+        let segments: Vec<&str> = input.key().get_segments().collect();
+        insert_property(
+            &mut schema,
+            &segments,
+            input.required(),
+            input_schema(input),
+        );
+    }
+    schema
+}
+
+/// Inserts `property` in the given object schema, creating intermediate
+/// objects for dot-separated keys.
+fn insert_property(
+    object: &mut Map<String, JsonValue>,
+    segments: &[&str],
+    required: bool,
+    property: JsonValue,
+) {
+    let (first, rest) = match segments.split_first() {
+        Some(split) => split,
+        None => return,
+    };
+
+    if required {
+        let required_keys = object.entry("required").or_insert_with(|| json!([]));
+        if let JsonValue::Array(keys) = required_keys {
+            if !keys.iter().any(|key| key == first) {
+                keys.push((*first).into());
+            }
+        }
+    }
+
+    let properties = object
+        .entry("properties")
+        .or_insert_with(|| JsonValue::Object(Map::new()));
+    if let JsonValue::Object(properties) = properties {
+        if rest.is_empty() {
+            properties.insert((*first).into(), property);
+        } else {
+            let child = properties
+                .entry(*first)
+                .or_insert_with(|| json!({ "type": "object", "properties": {} }));
+            if let JsonValue::Object(child) = child {
+                insert_property(child, rest, required, property);
+            }
+        }
+    }
+}
+
+fn input_schema(input: &InputTypes) -> JsonValue {
+    let mut schema = if let Some(text) = input.downcast_ref::<TextInput>() {
+        let mut schema = Map::new();
+        schema.insert("type".into(), "string".into());
+        if let Some(regex) = &text.validate_regex {
+            schema.insert("pattern".into(), regex.as_str().into());
+        }
+        // Required text inputs are invalid when empty
+        if text.info.required {
+            schema.insert("minLength".into(), 1.into());
+        }
+        schema
+    } else if input.downcast_ref::<BooleanInput>().is_some() {
+        let mut schema = Map::new();
+        schema.insert("type".into(), "boolean".into());
+        schema
+    } else if let Some(number) = input.downcast_ref::<NumberInput>() {
+        let is_integer = matches!(&number.step, Some(step) if !step.is_f64());
+        let mut schema = Map::new();
+        schema.insert(
+            "type".into(),
+            if is_integer { "integer" } else { "number" }.into(),
+        );
+        if let Some(min) = &number.min {
+            schema.insert("minimum".into(), JsonValue::Number(min.clone()));
+        }
+        if let Some(max) = &number.max {
+            schema.insert("maximum".into(), JsonValue::Number(max.clone()));
+        }
+        schema
+    } else if let Some(list) = input.downcast_ref::<ListInput>() {
+        let mut schema = Map::new();
+        schema.insert("type".into(), "array".into());
+        schema.insert(
+            "items".into(),
+            JsonValue::Object(object_schema(&list.inputs)),
+        );
+        if let Some(min) = list.min_length {
+            schema.insert("minItems".into(), min.into());
+        }
+        if let Some(max) = list.max_length {
+            schema.insert("maxItems".into(), max.into());
+        }
+        schema
+    } else if !input.children().is_empty() || input.downcast_ref::<GroupInput>().is_some() {
+        object_schema(input.children())
+    } else {
+        // Inputs of other kinds may hold any value
+        Map::new()
+    };
+
+    schema.insert("title".into(), input.name().into());
+    if let Some(description) = input.description() {
+        schema.insert("description".into(), description.into());
+    }
+    JsonValue::Object(schema)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::json_schema::inputs_from_json_schema;
+
+    fn inputs(json: JsonValue) -> Vec<InputTypes> {
+        serde_json::from_value(json).unwrap()
+    }
+
+    #[test]
+    fn scalar_inputs() {
+        let inputs = inputs(json!([
+            {
+                "type": "text",
+                "key": "date",
+                "name": "Date",
+                "description": "A date",
+                "required": true,
+                "validate_regex": "\\d{4}"
+            },
+            {"type": "number", "key": "age", "name": "Age", "min": 0, "max": 150, "step": 1},
+            {"type": "number", "key": "ratio", "name": "Ratio", "step": 0.1},
+            {"type": "boolean", "key": "is_active", "name": "Is active?"}
+        ]));
+        assert_eq!(
+            json_schema_from_inputs(&inputs),
+            json!({
+                "$schema": JSON_SCHEMA_DIALECT,
+                "type": "object",
+                "required": ["date"],
+                "properties": {
+                    "date": {
+                        "type": "string",
+                        "title": "Date",
+                        "description": "A date",
+                        "pattern": "\\d{4}",
+                        "minLength": 1
+                    },
+                    "age": {"type": "integer", "title": "Age", "minimum": 0, "maximum": 150},
+                    "ratio": {"type": "number", "title": "Ratio"},
+                    "is_active": {"type": "boolean", "title": "Is active?"}
+                }
+            })
+        );
+    }
+
+    #[test]
+    fn groups_lists_and_dotted_keys() {
+        let inputs = inputs(json!([
+            {
+                "type": "group",
+                "key": "author",
+                "name": "Author",
+                "inputs": [{"type": "text", "key": "name", "name": "Name"}]
+            },
+            {
+                "type": "list",
+                "key": "persons",
+                "name": "Persons",
+                "min_length": 1,
+                "max_length": 3,
+                "inputs": [{"type": "text", "key": "name", "name": "Name", "required": true}]
+            },
+            {"type": "text", "key": "license.name", "name": "License", "required": true}
+        ]));
+        assert_eq!(
+            json_schema_from_inputs(&inputs),
+            json!({
+                "$schema": JSON_SCHEMA_DIALECT,
+                "type": "object",
+                "required": ["license"],
+                "properties": {
+                    "author": {
+                        "type": "object",
+                        "title": "Author",
+                        "properties": {"name": {"type": "string", "title": "Name"}}
+                    },
+                    "persons": {
+                        "type": "array",
+                        "title": "Persons",
+                        "minItems": 1,
+                        "maxItems": 3,
+                        "items": {
+                            "type": "object",
+                            "required": ["name"],
+                            "properties": {
+                                "name": {"type": "string", "title": "Name", "minLength": 1}
+                            }
+                        }
+                    },
+                    "license": {
+                        "type": "object",
+                        "required": ["name"],
+                        "properties": {
+                            "name": {"type": "string", "title": "License", "minLength": 1}
+                        }
+                    }
+                }
+            })
+        );
+    }
+
+    #[test]
+    fn import_of_export_is_identity() {
+        let original = json!([
+            {
+                "type": "text",
+                "key": "date",
+                "name": "Date",
+                "description": "A date",
+                "required": true,
+                "validate_regex": "\\d{4}"
+            },
+            {
+                "type": "list",
+                "key": "persons",
+                "name": "Persons",
+                "description": null,
+                "min_length": 1,
+                "inputs": [{
+                    "type": "number",
+                    "key": "age",
+                    "name": "Age",
+                    "description": null,
+                    "min": 0,
+                    "step": 1
+                }]
+            }
+        ]);
+        let schema = json_schema_from_inputs(&inputs(original.clone()));
+        let imported = inputs_from_json_schema(&schema).unwrap();
+        assert_eq!(serde_json::to_value(imported).unwrap(), original);
+    }
+}
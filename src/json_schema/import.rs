@@ -0,0 +1,405 @@
+use crate::{inputs::*, Path};
+use anyhow::{anyhow, bail, Context, Result};
+use serde_json::Value as JsonValue;
+
+/// Maximum nesting of properties and of `$ref` indirections. Recursive
+/// schemas (eg. a tree node referencing itself) can't be represented as
+/// inputs, so they are rejected once this depth is reached.
+const MAX_DEPTH: usize = 32;
+
+/// Key of the single input generated for arrays of scalars, since list
+/// elements are always objects in `InputsData`.
+const LIST_ITEM_KEY: &str = "value";
+
+/// Converts a JSON Schema document into the inputs of a scenario.
+///
+/// The root schema must describe an object, each of its properties
+/// becoming an input. The supported keywords are `type`, `properties`,
+/// `required`, `items`, `minItems`, `maxItems`, `enum`, `pattern`,
+/// `minimum`, `maximum`, `title`, `description` and `$ref` pointing
+/// inside the document. Other keywords are ignored.
+pub fn inputs_from_json_schema(schema: &JsonValue) -> Result<Vec<InputTypes>> {
+    let converter = Converter { root: schema };
+    let root = converter.resolve(schema)?;
+    match schema_type(root) {
+        Some("object") => converter.object_properties(root, 0),
+        other => bail!(
+            "The root schema must describe an object, not: {}.",
+            other.unwrap_or("nothing")
+        ),
+    }
+}
+
+struct Converter<'a> {
+    root: &'a JsonValue,
+}
+
+impl<'a> Converter<'a> {
+    /// Follows the `$ref`s of `schema` until reaching a concrete schema.
+    fn resolve(&self, mut schema: &'a JsonValue) -> Result<&'a JsonValue> {
+        for _ in 0..MAX_DEPTH {
+            match schema.get("$ref") {
+                None => return Ok(schema),
+                Some(JsonValue::String(reference)) => {
+                    let pointer = reference.strip_prefix('#').ok_or_else(|| {
+                        anyhow!(
+                            "Only references within the document are supported: '{}'.",
+                            reference
+                        )
+                    })?;
+                    schema = self
+                        .root
+                        .pointer(pointer)
+                        .ok_or_else(|| anyhow!("Unresolved reference: '{}'.", reference))?;
+                }
+                Some(reference) => bail!("Invalid reference: {}.", reference),
+            }
+        }
+        bail!("Too many nested references, is there a cycle?")
+    }
+
+    fn object_properties(&self, schema: &'a JsonValue, depth: usize) -> Result<Vec<InputTypes>> {
+        let required: Vec<&str> = schema
+            .get("required")
+            .and_then(JsonValue::as_array)
+            .map(|required| required.iter().filter_map(JsonValue::as_str).collect())
+            .unwrap_or_default();
+
+        let properties = match schema.get("properties") {
+            Some(JsonValue::Object(properties)) => properties,
+            Some(_) => bail!("'properties' must be an object."),
+            None => return Ok(vec![]),
+        };
+
+        properties
+            .iter()
+            .map(|(key, property)| {
+                self.input(key, property, required.contains(&key.as_str()), depth)
+                    .with_context(|| format!("Invalid schema for property '{}'.", key))
+            })
+            .collect()
+    }
+
+    fn array_items(&self, schema: &'a JsonValue, depth: usize) -> Result<Vec<InputTypes>> {
+        let items = match schema.get("items") {
+            Some(items) => self.resolve(items)?,
+            None => return Ok(vec![]),
+        };
+
+        match schema_type(items) {
+            Some("object") => self.object_properties(items, depth),
+            _ => Ok(vec![self.input(LIST_ITEM_KEY, items, false, depth)?]),
+        }
+    }
+
+    fn input(
+        &self,
+        key: &str,
+        schema: &'a JsonValue,
+        required: bool,
+        depth: usize,
+    ) -> Result<InputTypes> {
+        if depth >= MAX_DEPTH {
+            bail!("The schema is nested too deeply, is it recursive?");
+        }
+
+        let schema = self.resolve(schema)?;
+        let mut info = InputInfo {
+            key: Path::from(key),
+            name: schema
+                .get("title")
+                .and_then(JsonValue::as_str)
+                .unwrap_or(key)
+                .to_owned(),
+            description: schema
+                .get("description")
+                .and_then(JsonValue::as_str)
+                .map(ToOwned::to_owned),
+            required,
+        };
+
+        Ok(match schema_type(schema) {
+            Some("object") => InputTypes::new(GroupInput {
+                info,
+                inputs: self.object_properties(schema, depth + 1)?,
+                show_disable_toggle: false,
+            }),
+            Some("array") => {
+                let list = ListInput {
+                    info,
+                    inputs: self.array_items(schema, depth + 1)?,
+                    min_length: schema.get("minItems").and_then(JsonValue::as_u64),
+                    max_length: schema.get("maxItems").and_then(JsonValue::as_u64),
+                };
+                list.check_lengths()
+                    .with_context(|| format!("Invalid array '{}'", key))?;
+                InputTypes::new(list)
+            }
+            Some("boolean") => InputTypes::new(BooleanInput { info }),
+            Some(t @ "number") | Some(t @ "integer") => InputTypes::new(NumberInput {
+                info,
+                min: number(schema, "minimum"),
+                max: number(schema, "maximum"),
+                step: if t == "integer" { Some(1.into()) } else { None },
+            }),
+            Some("string") | None => {
+                let validate_regex = match schema.get("enum") {
+                    Some(JsonValue::Array(values)) => {
+                        let values: Vec<_> = values.iter().map(enum_value_to_string).collect();
+                        let one_of = format!("One of: {}.", values.join(", "));
+                        info.description = Some(match info.description.take() {
+                            Some(description) => format!("{} {}", description, one_of),
+                            None => one_of,
+                        });
+                        Some(enum_regex(&values))
+                    }
+                    Some(_) => bail!("'enum' must be an array."),
+                    None => schema
+                        .get("pattern")
+                        .and_then(JsonValue::as_str)
+                        .map(ToOwned::to_owned),
+                };
+                let validate_regex = validate_regex
+                    .map(|pattern| ValidateRegex::new(&pattern))
+                    .transpose()
+                    .context("Invalid 'pattern'")?;
+                InputTypes::new(TextInput {
+                    info,
+                    validate_regex,
+                })
+            }
+            Some(other) => bail!("Unsupported type: '{}'.", other),
+        })
+    }
+}
+
+/// Returns the type described by `schema`, inferring it from the other
+/// keywords when `type` is absent. For a list of types (eg. `["string",
+/// "null"]`), the first non-null one is used.
+fn schema_type(schema: &JsonValue) -> Option<&str> {
+    match schema.get("type") {
+        Some(JsonValue::String(t)) => Some(t),
+        Some(JsonValue::Array(types)) => types
+            .iter()
+            .filter_map(JsonValue::as_str)
+            .find(|t| *t != "null"),
+        _ if schema.get("properties").is_some() => Some("object"),
+        _ if schema.get("items").is_some() => Some("array"),
+        _ => None,
+    }
+}
+
+fn number(schema: &JsonValue, keyword: &str) -> Option<JsonNumber> {
+    match schema.get(keyword) {
+        Some(JsonValue::Number(n)) => Some(n.clone()),
+        _ => None,
+    }
+}
+
+fn enum_value_to_string(value: &JsonValue) -> String {
+    match value {
+        JsonValue::String(s) => s.clone(),
+        _ => value.to_string(),
+    }
+}
+
+/// Builds an anchored regex only matching one of the given values.
+fn enum_regex(values: &[String]) -> String {
+    let alternatives: Vec<String> = values.iter().map(|value| regex::escape(value)).collect();
+    format!("^(?:{})$", alternatives.join("|"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn convert(schema: JsonValue) -> JsonValue {
+        serde_json::to_value(inputs_from_json_schema(&schema).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn scalar_properties() {
+        let schema = json!({
+            "type": "object",
+            "required": ["name"],
+            "properties": {
+                "name": {
+                    "type": "string",
+                    "title": "Name",
+                    "description": "The crate name",
+                    "pattern": "^[a-z_]+$"
+                },
+                "port": {"type": "integer", "minimum": 1, "maximum": 65535},
+                "ratio": {"type": "number", "minimum": 0.5},
+                "enabled": {"type": ["boolean", "null"]}
+            }
+        });
+        assert_eq!(
+            convert(schema),
+            json!([
+                {
+                    "type": "text",
+                    "key": "name",
+                    "name": "Name",
+                    "description": "The crate name",
+                    "required": true,
+                    "validate_regex": "^[a-z_]+$"
+                },
+                {
+                    "type": "number",
+                    "key": "port",
+                    "name": "port",
+                    "description": null,
+                    "min": 1,
+                    "max": 65535,
+                    "step": 1
+                },
+                {
+                    "type": "number",
+                    "key": "ratio",
+                    "name": "ratio",
+                    "description": null,
+                    "min": 0.5
+                },
+                {"type": "boolean", "key": "enabled", "name": "enabled", "description": null}
+            ])
+        );
+    }
+
+    #[test]
+    fn enum_becomes_an_anchored_regex() {
+        let schema = json!({
+            "properties": {
+                "edition": {"enum": ["2015", "2018"], "description": "Rust edition."},
+                "level": {"type": "string", "enum": ["a.b", 3]}
+            }
+        });
+        assert_eq!(
+            convert(schema),
+            json!([
+                {
+                    "type": "text",
+                    "key": "edition",
+                    "name": "edition",
+                    "description": "Rust edition. One of: 2015, 2018.",
+                    "validate_regex": "^(?:2015|2018)$"
+                },
+                {
+                    "type": "text",
+                    "key": "level",
+                    "name": "level",
+                    "description": "One of: a.b, 3.",
+                    "validate_regex": "^(?:a\\.b|3)$"
+                }
+            ])
+        );
+    }
+
+    #[test]
+    fn nested_objects_and_arrays() {
+        let schema = json!({
+            "type": "object",
+            "properties": {
+                "author": {
+                    "type": "object",
+                    "required": ["first_name"],
+                    "properties": {"first_name": {"type": "string"}}
+                },
+                "persons": {
+                    "type": "array",
+                    "minItems": 1,
+                    "maxItems": 10,
+                    "items": {
+                        "type": "object",
+                        "properties": {"age": {"type": "integer"}}
+                    }
+                },
+                "tags": {"type": "array", "items": {"type": "string"}}
+            }
+        });
+        assert_eq!(
+            convert(schema),
+            json!([
+                {
+                    "type": "group",
+                    "key": "author",
+                    "name": "author",
+                    "description": null,
+                    "show_disable_toggle": false,
+                    "inputs": [{
+                        "type": "text",
+                        "key": "first_name",
+                        "name": "first_name",
+                        "description": null,
+                        "required": true
+                    }]
+                },
+                {
+                    "type": "list",
+                    "key": "persons",
+                    "name": "persons",
+                    "description": null,
+                    "min_length": 1,
+                    "max_length": 10,
+                    "inputs": [{
+                        "type": "number",
+                        "key": "age",
+                        "name": "age",
+                        "description": null,
+                        "step": 1
+                    }]
+                },
+                {
+                    "type": "list",
+                    "key": "tags",
+                    "name": "tags",
+                    "description": null,
+                    "inputs": [{"type": "text", "key": "value", "name": "value", "description": null}]
+                }
+            ])
+        );
+    }
+
+    #[test]
+    fn references_within_the_document() {
+        let schema = json!({
+            "$ref": "#/definitions/root",
+            "definitions": {
+                "root": {
+                    "properties": {
+                        "home": {"$ref": "#/$defs/address"},
+                        "work": {"$ref": "#/$defs/address", "title": "ignored"}
+                    }
+                }
+            },
+            "$defs": {
+                "address": {
+                    "title": "Address",
+                    "properties": {"city": {"type": "string"}}
+                }
+            }
+        });
+        let inputs = convert(schema);
+        assert_eq!(inputs[0]["key"], json!("home"));
+        assert_eq!(inputs[0]["name"], json!("Address"));
+        assert_eq!(inputs[0]["inputs"][0]["key"], json!("city"));
+        assert_eq!(inputs[1]["key"], json!("work"));
+        assert_eq!(inputs[1]["inputs"], inputs[0]["inputs"]);
+    }
+
+    #[test]
+    fn invalid_schemas_are_rejected() {
+        [
+            json!({"type": "array", "items": {"type": "string"}}),
+            json!({"properties": {"a": {"$ref": "#/definitions/missing"}}}),
+            json!({"properties": {"a": {"$ref": "other.json#/a"}}}),
+            json!({"properties": {"a": {"type": "null"}}}),
+            json!({"properties": {"a": {"enum": "a"}}}),
+            json!({"$ref": "#/definitions/a", "definitions": {"a": {"$ref": "#/definitions/a"}}}),
+            json!({"properties": {"node": {"$ref": "#"}}}),
+        ]
+        .iter()
+        .for_each(|schema| assert!(inputs_from_json_schema(schema).is_err(), "{}", schema))
+    }
+}
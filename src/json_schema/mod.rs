@@ -0,0 +1,8 @@
+//! Conversions between scenario inputs and [JSON Schema](https://json-schema.org/)
+//! documents.
+
+mod export;
+mod import;
+
+pub use export::json_schema_from_inputs;
+pub use import::inputs_from_json_schema;
@@ -1,13 +1,44 @@
 #![recursion_limit = "1024"]
 
-mod agents;
+#[cfg(feature = "ui")]
+pub mod agents;
+#[cfg(feature = "ui")]
 pub mod app;
+pub mod batch;
+pub mod cli;
+#[cfg(feature = "ui")]
 mod components;
-mod inputs;
+mod diff;
+#[cfg(feature = "ui")]
+mod download;
+pub mod highlight;
+pub mod inputs;
 mod json_path;
+pub mod json_schema;
+pub mod migrations;
+pub mod output_format;
 mod prelude;
-mod scenario;
-mod template_engine;
-mod views;
+pub mod scenario;
+pub mod scenario_tests;
+pub mod template_analysis;
+pub mod template_engine;
+#[cfg(feature = "ui")]
+pub mod views;
+#[cfg(feature = "ui")]
+mod zip;
 
 pub use json_path::{InputsData, Path};
+pub use scenario::Scenario;
+pub use template_engine::{RenderedOutput, ScenarioEngine, TemplateEngine};
+
+/// Sets up the page and the render worker alike.
+///
+/// They run in separate WebAssembly instances, so a downstream crate with
+/// custom input kinds (see [`inputs::register_input_kind`]) calls `init`
+/// and then registers its kinds in both of its own binaries: the page,
+/// which also registers their views with `views::register_input_view`,
+/// and a `render_worker` binary, the name the page loads the worker from.
+#[cfg(feature = "ui")]
+pub fn init() {
+    web_logger::init();
+}
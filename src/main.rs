@@ -1,6 +1,6 @@
 use wasm_live_handlebars::app;
 
 fn main() {
-    web_logger::init();
+    wasm_live_handlebars::init();
     yew::start_app::<app::App>();
 }
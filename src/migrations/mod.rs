@@ -0,0 +1,155 @@
+//! Versions of the formats of scenarios and of the sessions saved in local
+//! storage, and the migrations of the documents written in older versions.
+//!
+//! Version 1 is every document written before the versions were recorded,
+//! which have no `format_version`. To change a format, add a migration to
+//! its chain, and a fixture of the previous version to the tests.
+
+use crate::prelude::*;
+
+/// The chain of migrations of a format.
+pub struct Migrations {
+    /// What the documents are, for the errors.
+    name: &'static str,
+    /// The migration at index `i` migrates a document from the version
+    /// `i + 1` to the version `i + 2`.
+    steps: &'static [fn(&mut JsonValue) -> Result<()>],
+}
+
+/// The format of scenarios.
+pub const SCENARIO: Migrations = Migrations {
+    name: "scenario",
+    steps: &[scenario_v2],
+};
+
+/// The format of the sessions saved in local storage.
+pub const SESSION: Migrations = Migrations {
+    name: "saved session",
+    steps: &[session_v2],
+};
+
+/// A document written by a newer version of the app, which this one can't
+/// tell how to read.
+#[derive(Debug)]
+pub struct UnsupportedVersion {
+    pub name: &'static str,
+    pub version: u64,
+    pub supported: u64,
+}
+
+impl fmt::Display for UnsupportedVersion {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "This {} has the format version {}, but this version of the app only supports \
+             up to {}. Update the app to open it.",
+            self.name, self.version, self.supported
+        )
+    }
+}
+
+impl std::error::Error for UnsupportedVersion {}
+
+impl Migrations {
+    /// The version of the documents written by this version of the app.
+    pub fn current_version(&self) -> u64 {
+        self.steps.len() as u64 + 1
+    }
+
+    /// Migrates a document from the version in its `format_version` to the
+    /// current one, which it is then stamped with.
+    pub fn migrate(&self, document: &mut JsonValue) -> Result<()> {
+        let version = match document.get("format_version") {
+            None => 1,
+            Some(version) => version
+                .as_u64()
+                .filter(|version| *version >= 1)
+                .ok_or_else(|| anyhow!("Invalid format_version: {}.", version))?,
+        };
+        if version > self.current_version() {
+            bail!(UnsupportedVersion {
+                name: self.name,
+                version,
+                supported: self.current_version(),
+            });
+        }
+
+        for (i, step) in self.steps.iter().enumerate().skip(version as usize - 1) {
+            step(document).with_context(|| {
+                format!(
+                    "Failed to migrate the {} to the format version {}",
+                    self.name,
+                    i + 2
+                )
+            })?;
+        }
+        if let JsonValue::Object(fields) = document {
+            fields.insert("format_version".into(), self.current_version().into());
+        }
+        Ok(())
+    }
+}
+
+/// Scenarios of version 1 are read as is, all the fields added since have
+/// default values.
+fn scenario_v2(_: &mut JsonValue) -> Result<()> {
+    Ok(())
+}
+
+/// Sessions of version 1 are the state of the app itself, which version 2
+/// saves in a `state` field next to the `format_version`.
+fn session_v2(session: &mut JsonValue) -> Result<()> {
+    let state = session.take();
+    *session = serde_json::json!({ "state": state });
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scenario::Scenario;
+    use serde_json::json;
+
+    pub(crate) const SCENARIO_V1: &str = include_str!("fixtures/scenario_v1.json");
+    pub(crate) const SCENARIO_V2: &str = include_str!("fixtures/scenario_v2.json");
+
+    #[test]
+    fn scenario_fixtures() {
+        for fixture in &[SCENARIO_V1, SCENARIO_V2] {
+            let scenario: Scenario = serde_json::from_str(fixture).unwrap();
+            assert_eq!(scenario.format_version, SCENARIO.current_version());
+            assert_eq!(scenario.templates[0].template.source(), "Hello {{name}}!");
+            assert_eq!(scenario.inputs.len(), 2);
+        }
+        let scenario: Scenario = serde_json::from_str(SCENARIO_V2).unwrap();
+        assert_eq!(
+            serde_json::to_value(&scenario).unwrap(),
+            serde_json::from_str::<JsonValue>(SCENARIO_V2).unwrap()
+        );
+    }
+
+    #[test]
+    fn session_v1() {
+        let mut session = json!("Init");
+        SESSION.migrate(&mut session).unwrap();
+        assert_eq!(session, json!({"format_version": 2, "state": "Init"}));
+    }
+
+    #[test]
+    fn unsupported_versions() {
+        let mut scenario: JsonValue = serde_json::from_str(SCENARIO_V2).unwrap();
+        scenario["format_version"] = json!(SCENARIO.current_version() + 1);
+        let error = SCENARIO.migrate(&mut scenario.clone()).unwrap_err();
+        assert!(error.is::<UnsupportedVersion>());
+        assert!(serde_json::from_value::<Scenario>(scenario)
+            .unwrap_err()
+            .to_string()
+            .starts_with("This scenario has the format version 3, "));
+
+        let mut session = json!({"format_version": "2"});
+        assert_eq!(
+            SESSION.migrate(&mut session).unwrap_err().to_string(),
+            "Invalid format_version: \"2\"."
+        );
+    }
+}
@@ -0,0 +1,202 @@
+//! Validation of the outputs declaring a data format, so that a stray
+//! comma in a template is noticed as soon as it is rendered.
+
+use crate::{
+    highlight::Language,
+    template_engine::{capitalize, excerpt},
+};
+use core::fmt;
+use serde::{Deserialize, Serialize};
+
+/// The data format of an output, checked with its parser after each render.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum Format {
+    Json,
+    #[serde(alias = "yml")]
+    Yaml,
+    Toml,
+}
+
+/// An output which doesn't parse in its declared format.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct FormatError {
+    pub format: Format,
+    /// Line and column, both starting at 1.
+    pub position: Option<(usize, usize)>,
+    pub reason: String,
+}
+
+impl Format {
+    pub fn name(self) -> &'static str {
+        match self {
+            Format::Json => "JSON",
+            Format::Yaml => "YAML",
+            Format::Toml => "TOML",
+        }
+    }
+
+    pub fn language(self) -> Language {
+        match self {
+            Format::Json => Language::Json,
+            Format::Yaml => Language::Yaml,
+            Format::Toml => Language::Toml,
+        }
+    }
+
+    /// Checks that `content` parses in this format.
+    pub fn validate(self, content: &str) -> Result<(), FormatError> {
+        match self {
+            Format::Json => serde_json::from_str::<serde_json::Value>(content)
+                .map(drop)
+                .map_err(|e| self.json_error(e)),
+            Format::Yaml => yaml_documents(content).map(drop),
+            Format::Toml => toml::from_str::<toml::Value>(content)
+                .map(drop)
+                .map_err(|e| self.toml_error(e)),
+        }
+    }
+
+    /// Parses `content` and prints it back with the default layout of the
+    /// format, keeping the order of the keys.
+    pub fn pretty_print(self, content: &str) -> Result<String, FormatError> {
+        let printed = match self {
+            Format::Json => {
+                let value: serde_json::Value =
+                    serde_json::from_str(content).map_err(|e| self.json_error(e))?;
+                serde_json::to_string_pretty(&value).map_err(|e| self.json_error(e))?
+            }
+            Format::Yaml => yaml_documents(content)?
+                .iter()
+                .map(|document| {
+                    serde_yaml::to_string(document)
+                        .map(|printed| printed.trim_start_matches("---\n").trim_end().to_owned())
+                        .map_err(|e| self.yaml_error(e))
+                })
+                .collect::<Result<Vec<_>, _>>()?
+                .join("\n---\n"),
+            Format::Toml => {
+                let value: toml::Value = toml::from_str(content).map_err(|e| self.toml_error(e))?;
+                return toml::to_string_pretty(&value).map_err(|e| self.error(e.to_string(), None));
+            }
+        };
+        Ok(printed + "\n")
+    }
+
+    fn error(self, reason: String, position: Option<(usize, usize)>) -> FormatError {
+        // The parsers append the position to their messages.
+        let reason = match reason.rfind(" at line ") {
+            Some(index) if position.is_some() => reason[..index].to_owned(),
+            _ => reason,
+        };
+        FormatError {
+            format: self,
+            position,
+            reason: capitalize(&reason),
+        }
+    }
+
+    fn json_error(self, error: serde_json::Error) -> FormatError {
+        let position = Some((error.line(), error.column().max(1)));
+        self.error(error.to_string(), position)
+    }
+
+    fn yaml_error(self, error: serde_yaml::Error) -> FormatError {
+        let reason = error.to_string();
+        // Columns of YAML errors start at 0, lines at 1. The errors of
+        // streams of documents only have their position in their message.
+        let position = error
+            .location()
+            .map(|location| (location.line(), location.column() + 1))
+            .or_else(|| {
+                let (line, column) = reason.rsplit(" at line ").next()?.split_once(" column ")?;
+                Some((line.parse().ok()?, column.parse().ok()?))
+            });
+        self.error(reason, position)
+    }
+
+    fn toml_error(self, error: toml::de::Error) -> FormatError {
+        let position = error
+            .line_col()
+            .map(|(line, column)| (line + 1, column + 1));
+        self.error(error.to_string(), position)
+    }
+}
+
+/// Parses the documents of a YAML stream, separated by `---`.
+fn yaml_documents(content: &str) -> Result<Vec<serde_yaml::Value>, FormatError> {
+    serde_yaml::Deserializer::from_str(content)
+        .map(|document| {
+            serde_yaml::Value::deserialize(document).map_err(|e| Format::Yaml.yaml_error(e))
+        })
+        .collect()
+}
+
+impl FormatError {
+    /// Shows the line of the output where the error is, see [`excerpt`].
+    pub fn excerpt(&self, source: &str) -> Option<String> {
+        excerpt(source, self.position?)
+    }
+}
+
+impl fmt::Display for FormatError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Invalid {}: {}", self.format.name(), self.reason)?;
+        if let Some((line, column)) = self.position {
+            write!(f, " at line {}, column {}", line, column)?;
+        }
+        write!(f, ".")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn valid_outputs() {
+        assert_eq!(Format::Json.validate("{\"a\": [1, 2]}"), Ok(()));
+        assert_eq!(Format::Yaml.validate("a: 1\n---\nb: [2]"), Ok(()));
+        assert_eq!(Format::Yaml.validate(""), Ok(()));
+        assert_eq!(Format::Toml.validate("a = 1\n[b]\nc = 'd'"), Ok(()));
+    }
+
+    #[test]
+    fn error_positions() {
+        let json = "{\n  \"a\": 1,\n}";
+        let error = Format::Json.validate(json).unwrap_err();
+        assert_eq!(error.position, Some((3, 1)));
+        assert_eq!(
+            error.to_string(),
+            "Invalid JSON: Trailing comma at line 3, column 1."
+        );
+        assert_eq!(error.excerpt(json).unwrap(), "3 | }\n    ^");
+
+        let error = Format::Yaml.validate("a: 1\nb: [2\n").unwrap_err();
+        assert_eq!(error.position, Some((3, 1)));
+
+        let error = Format::Toml.validate("a = 1\nb = \n").unwrap_err();
+        assert_eq!(error.position.map(|(line, _)| line), Some(2));
+        assert!(error.to_string().starts_with("Invalid TOML: "));
+    }
+
+    #[test]
+    fn pretty_print() {
+        assert_eq!(
+            Format::Json
+                .pretty_print("{\"b\": 1, \"a\": [true]}")
+                .unwrap(),
+            "{\n  \"b\": 1,\n  \"a\": [\n    true\n  ]\n}\n"
+        );
+        assert_eq!(
+            Format::Yaml
+                .pretty_print("b:   1\na: {c: x}\n---\n- 1")
+                .unwrap(),
+            "b: 1\na:\n  c: x\n---\n- 1\n"
+        );
+        assert_eq!(
+            Format::Toml.pretty_print("[t]\nx=1\n").unwrap(),
+            "[t]\nx = 1\n"
+        );
+    }
+}
@@ -3,6 +3,8 @@ pub use core::fmt;
 pub use log::*;
 pub use serde::{Deserialize, Serialize};
 pub use serde_json::Value as JsonValue;
+#[cfg(feature = "ui")]
 pub use yew::prelude::*;
 
+#[cfg(feature = "ui")]
 pub use crate::components::NeqAssign;
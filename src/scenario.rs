@@ -1,11 +1,98 @@
-use crate::{inputs::InputTypes, prelude::*};
+use crate::{
+    highlight::Language,
+    inputs::InputTypes,
+    json_schema, migrations,
+    output_format::Format,
+    prelude::*,
+    template_engine::{Engine, Escape},
+};
+use std::{borrow::Cow, collections::BTreeMap, convert::TryFrom};
 
-/// A scenario represents the template to be rendered and the format
-/// of inputs needed to generate it.
+/// Name of the template of scenarios declaring a single `template`.
+pub const MAIN_TEMPLATE_NAME: &str = "main";
+
+/// A scenario represents the templates to be rendered and the format
+/// of inputs needed to generate them.
 #[derive(Serialize, Deserialize, Debug)]
+#[serde(try_from = "JsonValue")]
 pub struct Scenario {
-    pub template: Template,
+    /// The version of the format of the scenario, see [`crate::migrations`].
+    /// Older scenarios are migrated when read, so this is always the
+    /// current version.
+    pub format_version: u64,
+    #[serde(default, skip_serializing_if = "Metadata::is_empty")]
+    pub metadata: Metadata,
+    #[serde(default, skip_serializing_if = "Engine::is_default")]
+    pub engine: Engine,
+    pub templates: Vec<NamedTemplate>,
+    /// Helpers written in [Rhai](https://rhai.rs), by name. They receive
+    /// the parameters in the `params` array and the hash in `hash`.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub helpers: BTreeMap<String, String>,
+    /// Whether rendering fails when a template reads a missing variable,
+    /// instead of rendering it as an empty string.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub strict: bool,
+    /// How the values rendered by `{{...}}` are escaped.
+    #[serde(default, skip_serializing_if = "Escape::is_default")]
+    pub escape: Escape,
     pub inputs: Vec<InputTypes>,
+    /// Cases checking the outputs rendered from given data, see
+    /// [`crate::scenario_tests`].
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tests: Vec<TestCase>,
+}
+
+/// Describes a scenario to its users, none of it is needed to render it.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct Metadata {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    /// Version of the scenario itself, eg. `1.2.0`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub version: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub authors: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub homepage: Option<String>,
+}
+
+/// A test of a scenario: the outputs expected from rendering `data`, by
+/// template name. Outputs which aren't listed aren't checked.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct TestCase {
+    pub name: String,
+    pub data: JsonValue,
+    /// Written like templates, either as a string or as a list of lines.
+    pub expected: BTreeMap<String, Template>,
+}
+
+/// A template of a scenario. Every template can be included by the
+/// others as a partial, eg. `{{> header}}`, but only outputs are rendered.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct NamedTemplate {
+    pub name: String,
+    pub template: Template,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub output: bool,
+    /// Template of the name of the file rendered from an output, eg.
+    /// `src/{{module}}.rs`. Defaults to the name of the template.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub file_name: Option<String>,
+    /// Language of the output, used to highlight it. Guessed from the
+    /// extension of the file name if not given.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub language: Option<Language>,
+    /// Data format of the output, which is parsed after each render.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub format: Option<Format>,
+    /// Whether to print back the output parsed in its format.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub pretty: bool,
 }
 
 #[derive(Serialize, Deserialize, Debug)]
@@ -15,7 +102,319 @@ pub enum Template {
     StringListTemplate(Vec<String>),
 }
 
-impl Scenario {}
+impl NamedTemplate {
+    fn main(template: Template) -> Self {
+        Self {
+            name: MAIN_TEMPLATE_NAME.to_owned(),
+            template,
+            output: true,
+            file_name: None,
+            language: None,
+            format: None,
+            pretty: false,
+        }
+    }
+
+    /// Returns the declared language of the output, or the one of its
+    /// format or of its rendered file name.
+    pub fn language(&self, file_name: &str) -> Option<Language> {
+        self.language
+            .or_else(|| self.format.map(Format::language))
+            .or_else(|| Language::from_file_name(file_name))
+    }
+}
+
+impl Metadata {
+    pub fn is_empty(&self) -> bool {
+        *self == Metadata::default()
+    }
+
+    /// Returns the homepage if it is an `http` or `https` URL, which is
+    /// safe to link to, unlike eg. a `javascript:` one.
+    pub fn homepage_url(&self) -> Option<&str> {
+        let homepage = self.homepage.as_deref()?;
+        let lowercase = homepage.to_ascii_lowercase();
+        let rest = lowercase
+            .strip_prefix("https://")
+            .or_else(|| lowercase.strip_prefix("http://"))?;
+        match rest.chars().next() {
+            Some(c) if c.is_ascii_alphanumeric() || c == '[' => Some(homepage),
+            _ => None,
+        }
+    }
+}
+
+impl Template {
+    /// Returns the template as a single string.
+    pub fn source(&self) -> Cow<'_, str> {
+        match self {
+            Template::StringTemplate(s) => Cow::Borrowed(s),
+            Template::StringListTemplate(ls) => Cow::Owned(ls.join("\n")),
+        }
+    }
+}
+
+impl Scenario {
+    /// Creates a scenario with a single template, named
+    /// [`MAIN_TEMPLATE_NAME`].
+    pub fn new(template: Template, inputs: Vec<InputTypes>) -> Self {
+        Self {
+            format_version: migrations::SCENARIO.current_version(),
+            metadata: Metadata::default(),
+            engine: Engine::default(),
+            templates: vec![NamedTemplate::main(template)],
+            helpers: BTreeMap::new(),
+            strict: false,
+            escape: Escape::default(),
+            inputs,
+            tests: vec![],
+        }
+    }
+
+    /// Returns the title of the scenario, or a placeholder if it has none.
+    pub fn title(&self) -> &str {
+        self.metadata
+            .title
+            .as_deref()
+            .unwrap_or("Untitled scenario")
+    }
+
+    /// Returns the templates to render, in order of declaration.
+    pub fn outputs(&self) -> impl Iterator<Item = &NamedTemplate> {
+        self.templates.iter().filter(|template| template.output)
+    }
+
+    /// Returns a JSON Schema describing the data produced by the inputs
+    /// of this scenario.
+    pub fn inputs_json_schema(&self) -> JsonValue {
+        json_schema::json_schema_from_inputs(&self.inputs)
+    }
+}
+
+/// The scenarios as written by users, either with a single `template` or
+/// with a list of named `templates`, once migrated to the current format.
+#[derive(Deserialize)]
+struct ScenarioDefinition {
+    #[serde(default)]
+    metadata: Metadata,
+    #[serde(default)]
+    engine: Engine,
+    template: Option<Template>,
+    #[serde(default)]
+    templates: Vec<NamedTemplate>,
+    #[serde(default)]
+    helpers: BTreeMap<String, String>,
+    #[serde(default)]
+    strict: bool,
+    #[serde(default)]
+    escape: Escape,
+    inputs: Vec<InputTypes>,
+    #[serde(default)]
+    tests: Vec<TestCase>,
+}
+
+impl TryFrom<JsonValue> for Scenario {
+    type Error = anyhow::Error;
+
+    fn try_from(mut value: JsonValue) -> Result<Self> {
+        migrations::SCENARIO.migrate(&mut value)?;
+        let definition = ScenarioDefinition::deserialize(value)?;
+        Scenario::try_from(definition)
+    }
+}
+
+impl TryFrom<ScenarioDefinition> for Scenario {
+    type Error = anyhow::Error;
+
+    fn try_from(definition: ScenarioDefinition) -> Result<Self> {
+        let templates = match definition.template {
+            Some(_) if !definition.templates.is_empty() => {
+                bail!("A scenario can't have both a 'template' and 'templates'.")
+            }
+            Some(template) => vec![NamedTemplate::main(template)],
+            None => definition.templates,
+        };
+        let scenario = Scenario {
+            format_version: migrations::SCENARIO.current_version(),
+            metadata: definition.metadata,
+            engine: definition.engine,
+            templates,
+            helpers: definition.helpers,
+            strict: definition.strict,
+            escape: definition.escape,
+            inputs: definition.inputs,
+            tests: definition.tests,
+        };
+
+        for (i, template) in scenario.templates.iter().enumerate() {
+            if scenario.templates[..i]
+                .iter()
+                .any(|other| other.name == template.name)
+            {
+                bail!("Duplicate template name: '{}'.", template.name);
+            }
+        }
+        if scenario.outputs().next().is_none() {
+            bail!("A scenario must have at least one output template.");
+        }
+        for test in &scenario.tests {
+            if let Some(name) = test
+                .expected
+                .keys()
+                .find(|name| !scenario.outputs().any(|output| &output.name == *name))
+            {
+                bail!(
+                    "The test '{}' expects the output of '{}', which isn't an output template.",
+                    test.name,
+                    name
+                );
+            }
+        }
+
+        Ok(scenario)
+    }
+}
 
 #[allow(unused)]
 pub struct ScenarioAsJson<'a>(&'a Scenario);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn single_template() {
+        let scenario: Scenario =
+            serde_json::from_value(json!({"template": ["a", "b"], "inputs": []})).unwrap();
+        assert_eq!(scenario.templates.len(), 1);
+        assert_eq!(scenario.templates[0].name, MAIN_TEMPLATE_NAME);
+        assert_eq!(scenario.templates[0].template.source(), "a\nb");
+        assert!(scenario.templates[0].output);
+    }
+
+    #[test]
+    fn named_templates() {
+        let definition = json!({
+            "templates": [
+                {"name": "header", "template": "# {{title}}"},
+                {"name": "README.md", "template": "{{> header}}", "output": true},
+                {
+                    "name": "main",
+                    "template": "fn main() {}",
+                    "output": true,
+                    "file_name": "src/{{name}}.rs"
+                }
+            ],
+            "inputs": []
+        });
+        let scenario: Scenario = serde_json::from_value(definition.clone()).unwrap();
+        let outputs: Vec<&str> = scenario.outputs().map(|t| t.name.as_str()).collect();
+        assert_eq!(outputs, vec!["README.md", "main"]);
+        assert_eq!(
+            serde_json::to_value(&scenario).unwrap(),
+            json!({"format_version": 2, "templates": definition["templates"], "inputs": []})
+        );
+    }
+
+    #[test]
+    fn metadata() {
+        let definition = json!({
+            "metadata": {
+                "title": "Crate",
+                "version": "1.0.0",
+                "authors": ["Ann", "Bob"],
+                "tags": ["rust"]
+            },
+            "template": "",
+            "inputs": []
+        });
+        let scenario: Scenario = serde_json::from_value(definition).unwrap();
+        assert_eq!(scenario.title(), "Crate");
+        assert_eq!(scenario.metadata.authors, vec!["Ann", "Bob"]);
+        assert_eq!(
+            serde_json::to_value(&scenario).unwrap()["metadata"],
+            json!({"title": "Crate", "version": "1.0.0", "authors": ["Ann", "Bob"], "tags": ["rust"]})
+        );
+
+        let homepage_url = |homepage: &str| {
+            Metadata {
+                homepage: Some(homepage.to_owned()),
+                ..Metadata::default()
+            }
+            .homepage_url()
+            .map(ToOwned::to_owned)
+        };
+        assert_eq!(
+            homepage_url("HTTPS://example.com/a"),
+            Some("HTTPS://example.com/a".to_owned())
+        );
+        assert!(homepage_url("http://[::1]:8080").is_some());
+        for unsafe_url in &[
+            "javascript:alert(1)",
+            " https://a.b",
+            "https:///x",
+            "//a.b",
+            "a.b",
+        ] {
+            assert_eq!(homepage_url(unsafe_url), None, "{}", unsafe_url);
+        }
+
+        let scenario = Scenario::new(Template::StringTemplate(String::new()), vec![]);
+        assert_eq!(scenario.title(), "Untitled scenario");
+        assert!(serde_json::to_value(&scenario)
+            .unwrap()
+            .get("metadata")
+            .is_none());
+    }
+
+    #[test]
+    fn invalid_templates() {
+        [
+            json!({"inputs": []}),
+            json!({"templates": [{"name": "a", "template": ""}], "inputs": []}),
+            json!({
+                "template": "",
+                "templates": [{"name": "a", "template": "", "output": true}],
+                "inputs": []
+            }),
+            json!({
+                "templates": [
+                    {"name": "a", "template": "", "output": true},
+                    {"name": "a", "template": ""}
+                ],
+                "inputs": []
+            }),
+            json!({
+                "templates": [
+                    {"name": "a", "template": ""},
+                    {"name": "b", "template": "", "output": true}
+                ],
+                "inputs": [],
+                "tests": [{"name": "t", "data": {}, "expected": {"a": ""}}]
+            }),
+        ]
+        .iter()
+        .for_each(|definition| {
+            assert!(serde_json::from_value::<Scenario>(definition.clone()).is_err())
+        })
+    }
+
+    #[test]
+    fn output_languages() {
+        let scenario: Scenario = serde_json::from_value(json!({
+            "templates": [
+                {"name": "a", "template": "", "output": true, "language": "bash"},
+                {"name": "b", "template": "", "output": true, "file_name": "{{b}}.yml"}
+            ],
+            "inputs": []
+        }))
+        .unwrap();
+        let languages: Vec<Option<Language>> = scenario
+            .outputs()
+            .map(|output| output.language("x.json"))
+            .collect();
+        assert_eq!(languages, vec![Some(Language::Sh), Some(Language::Json)]);
+        assert_eq!(scenario.templates[1].language("b.txt"), None);
+    }
+}
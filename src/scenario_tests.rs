@@ -0,0 +1,238 @@
+//! Tests of scenarios, declared in their `tests` section: each case
+//! renders the outputs from its data and compares them to the expected ones.
+//!
+//! The same runner is used by the app and by [`test_scenario_files`], to
+//! check the scenarios of a directory from `cargo test`.
+
+use crate::{
+    diff::{diff_lines, DiffLine, LineChange},
+    prelude::*,
+    scenario::{Scenario, TestCase},
+    template_engine::{CompileError, ScenarioEngine, TemplateEngine},
+};
+use std::{fs, path::PathBuf};
+
+/// The result of a test case.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TestReport {
+    pub name: String,
+    /// The expected outputs which differ from the rendered ones, in order of
+    /// declaration.
+    pub failures: Vec<OutputMismatch>,
+}
+
+/// An expected output which differs from the rendered one.
+#[derive(Debug, Clone, PartialEq)]
+pub struct OutputMismatch {
+    pub template: String,
+    pub expected: String,
+    /// The rendered output, or the error which prevented rendering it.
+    pub actual: Result<String, String>,
+}
+
+impl TestReport {
+    pub fn passed(&self) -> bool {
+        self.failures.is_empty()
+    }
+}
+
+impl OutputMismatch {
+    /// Returns the lines of the rendered output, and the expected lines
+    /// removed from it, if it could be rendered.
+    pub fn diff(&self) -> Option<Vec<DiffLine>> {
+        let actual = self.actual.as_ref().ok()?;
+        Some(diff_lines(&self.expected, actual))
+    }
+}
+
+impl fmt::Display for OutputMismatch {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.diff() {
+            Some(diff) => {
+                writeln!(f, "'{}' differs (-expected +actual):", self.template)?;
+                for line in diff {
+                    let marker = match line.change {
+                        LineChange::Unchanged => ' ',
+                        LineChange::Inserted => '+',
+                        LineChange::Removed => '-',
+                    };
+                    writeln!(f, "{}{}", marker, line.content)?;
+                }
+                Ok(())
+            }
+            None => writeln!(
+                f,
+                "'{}' failed to render: {}",
+                self.template,
+                self.actual.as_ref().unwrap_err()
+            ),
+        }
+    }
+}
+
+/// Runs the test cases of a scenario with its own engine and strict mode.
+pub(crate) fn run_tests(scenario: &Scenario) -> Result<Vec<TestReport>, Vec<CompileError>> {
+    let engine = ScenarioEngine::with_scenario(scenario)?;
+    Ok(scenario
+        .tests
+        .iter()
+        .map(|test| run_test(&engine, scenario, test))
+        .collect())
+}
+
+/// Renders the outputs of a test case. Outputs are compared without their
+/// trailing newlines, which templates written as lists of lines can't end
+/// with.
+fn run_test<E: TemplateEngine>(engine: &E, scenario: &Scenario, test: &TestCase) -> TestReport {
+    let outputs = engine.render_outputs(scenario, &test.data);
+    let failures = scenario
+        .outputs()
+        .zip(outputs)
+        .filter_map(|(template, output)| {
+            let expected = test.expected.get(&template.name)?.source();
+            match output.content {
+                Ok(actual) if actual.trim_end_matches('\n') == expected.trim_end_matches('\n') => {
+                    None
+                }
+                content => Some(OutputMismatch {
+                    template: template.name.clone(),
+                    expected: expected.into_owned(),
+                    actual: content.map_err(|e| format!("{:#}", e)),
+                }),
+            }
+        })
+        .collect();
+    TestReport {
+        name: test.name.clone(),
+        failures,
+    }
+}
+
+/// Runs the tests of the scenarios of a directory, its `*.json` files, and
+/// returns the number of test cases which passed. Fails with the
+/// description of every failed test, so it can be used from `cargo test`:
+///
+/// ```ignore
+/// #[test]
+/// fn scenarios() {
+///     wasm_live_handlebars::scenario_tests::test_scenario_files("scenarios").unwrap();
+/// }
+/// ```
+pub fn test_scenario_files(dir: impl AsRef<std::path::Path>) -> Result<usize> {
+    let dir = dir.as_ref();
+    let mut files = fs::read_dir(dir)
+        .with_context(|| format!("Failed to read the directory {}", dir.display()))?
+        .map(|entry| entry.map(|entry| entry.path()))
+        .collect::<Result<Vec<PathBuf>, _>>()?;
+    files.retain(|file| {
+        file.extension()
+            .is_some_and(|extension| extension == "json")
+    });
+    files.sort();
+
+    let mut passed = 0;
+    let mut failures = String::new();
+    for file in files {
+        let scenario: Scenario = fs::read_to_string(&file)
+            .map_err(anyhow::Error::from)
+            .and_then(|content| Ok(serde_json::from_str(&content)?))
+            .with_context(|| format!("Failed to load the scenario {}", file.display()))?;
+        let reports = match run_tests(&scenario) {
+            Ok(reports) => reports,
+            Err(errors) => {
+                for error in errors {
+                    failures += &format!("{}: {}\n", file.display(), error);
+                }
+                continue;
+            }
+        };
+        for report in reports {
+            if report.passed() {
+                passed += 1;
+                continue;
+            }
+            failures += &format!("{}: test '{}' failed:\n", file.display(), report.name);
+            for failure in report.failures {
+                failures += &failure.to_string();
+            }
+        }
+    }
+
+    if failures.is_empty() {
+        Ok(passed)
+    } else {
+        bail!("Some scenario tests failed.\n{}", failures)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn scenario(tests: JsonValue) -> Scenario {
+        serde_json::from_value(json!({
+            "templates": [
+                {"name": "greeting", "template": "Hello {{name}}!\n", "output": true},
+                {"name": "list", "template": "{{#each items}}- {{this}}\n{{/each}}", "output": true}
+            ],
+            "strict": true,
+            "inputs": [],
+            "tests": tests
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn passing_and_failing_tests() {
+        let reports = run_tests(&scenario(json!([
+            {
+                "name": "passes",
+                "data": {"name": "Bob", "items": [1, 2]},
+                "expected": {"greeting": "Hello Bob!", "list": ["- 1", "- 2"]}
+            },
+            {
+                "name": "fails",
+                "data": {"items": [1]},
+                "expected": {"greeting": "Hello Bob!", "list": ["- 1", "- 2"]}
+            }
+        ])))
+        .unwrap();
+
+        assert!(reports[0].passed());
+        assert_eq!(reports[1].name, "fails");
+        let failures = &reports[1].failures;
+        assert_eq!(failures.len(), 2);
+        assert!(failures[0].actual.is_err());
+        assert!(failures[0]
+            .to_string()
+            .starts_with("'greeting' failed to render: "));
+        assert_eq!(
+            failures[1].to_string(),
+            "'list' differs (-expected +actual):\n - 1\n-- 2\n"
+        );
+    }
+
+    #[test]
+    fn scenario_files() {
+        let dir = std::env::temp_dir().join(format!("scenario-tests-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let write = |name: &str, tests: JsonValue| {
+            let content = serde_json::to_string(&scenario(tests)).unwrap();
+            fs::write(dir.join(name), content).unwrap();
+        };
+        let test = |name: &str, greeting: &str| json!({"name": name, "data": {"name": "Bob"}, "expected": {"greeting": greeting}});
+        write(
+            "a.json",
+            json!([test("a1", "Hello Bob!"), test("a2", "Hello Bob!")]),
+        );
+        fs::write(dir.join("notes.txt"), "Not a scenario").unwrap();
+        assert_eq!(test_scenario_files(&dir).unwrap(), 2);
+
+        write("b.json", json!([test("b1", "Hi Bob!")]));
+        let error = test_scenario_files(&dir).unwrap_err().to_string();
+        fs::remove_dir_all(&dir).unwrap();
+        assert!(error.contains("b.json: test 'b1' failed:\n"));
+        assert!(error.ends_with("-Hi Bob!\n+Hello Bob!\n"));
+    }
+}
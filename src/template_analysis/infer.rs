@@ -0,0 +1,151 @@
+use super::{referenced_variables, Segment, Usage};
+use crate::{inputs::*, Path};
+use anyhow::Result;
+
+/// Generates starter inputs for the variables read by a template: lists
+/// for `#each`, groups for `#with` and nested paths, booleans for
+/// variables only tested by `#if` / `#unless`, and text inputs for the
+/// rest.
+pub fn infer_inputs(source: &str) -> Result<Vec<InputTypes>> {
+    let mut root = Node::default();
+    for reference in referenced_variables(source)? {
+        root.insert(&reference.path, reference.usage);
+    }
+    Ok(root
+        .children
+        .into_iter()
+        .map(|(key, node)| node.into_input(key))
+        .collect())
+}
+
+#[derive(Default)]
+struct Node {
+    children: Vec<(String, Node)>,
+    is_list: bool,
+    is_context: bool,
+    is_rendered: bool,
+}
+
+impl Node {
+    fn insert(&mut self, path: &[Segment], usage: Usage) {
+        match path.split_first() {
+            None => match usage {
+                Usage::Value => self.is_rendered = true,
+                Usage::Condition => {}
+                Usage::List => self.is_list = true,
+                Usage::Context => self.is_context = true,
+            },
+            // The children of a list node are the fields of its elements.
+            Some((Segment::Item, rest)) => {
+                self.is_list = true;
+                self.insert(rest, usage);
+            }
+            Some((Segment::Key(key), rest)) => {
+                let index = match self.children.iter().position(|(k, _)| k == key) {
+                    Some(index) => index,
+                    None => {
+                        self.children.push((key.clone(), Node::default()));
+                        self.children.len() - 1
+                    }
+                };
+                self.children[index].1.insert(rest, usage);
+            }
+        }
+    }
+
+    fn into_input(self, key: String) -> InputTypes {
+        let info = InputInfo {
+            name: humanize(&key),
+            key: Path::from(key),
+            description: None,
+            required: false,
+        };
+        let is_group = self.is_context || !self.children.is_empty();
+        let inputs: Vec<InputTypes> = self
+            .children
+            .into_iter()
+            .map(|(key, node)| node.into_input(key))
+            .collect();
+
+        if self.is_list {
+            InputTypes::new(ListInput {
+                info,
+                inputs,
+                min_length: None,
+                max_length: None,
+            })
+        } else if is_group {
+            InputTypes::new(GroupInput {
+                info,
+                inputs,
+                show_disable_toggle: false,
+            })
+        } else if !self.is_rendered {
+            InputTypes::new(BooleanInput { info })
+        } else {
+            InputTypes::new(TextInput {
+                info,
+                validate_regex: None,
+            })
+        }
+    }
+}
+
+/// Turns a key like `first_name` into a name like `First name`.
+fn humanize(key: &str) -> String {
+    let spaced = key.replace(['_', '-'], " ");
+    let mut chars = spaced.trim().chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => key.to_owned(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn infer_the_debug_scenario() {
+        let inputs = infer_inputs(include_str!("../input_template.hbs")).unwrap();
+        assert_eq!(
+            serde_json::to_value(inputs).unwrap(),
+            json!([
+                {"type": "text", "key": "date", "name": "Date", "description": null},
+                {"type": "text", "key": "time", "name": "Time", "description": null},
+                {
+                    "type": "group",
+                    "key": "author",
+                    "name": "Author",
+                    "description": null,
+                    "show_disable_toggle": false,
+                    "inputs": [
+                        {"type": "text", "key": "first_name", "name": "First name", "description": null},
+                        {"type": "text", "key": "last_name", "name": "Last name", "description": null},
+                        {"type": "text", "key": "age", "name": "Age", "description": null}
+                    ]
+                },
+                {
+                    "type": "list",
+                    "key": "persons",
+                    "name": "Persons",
+                    "description": null,
+                    "inputs": [
+                        {"type": "text", "key": "first_name", "name": "First name", "description": null},
+                        {"type": "text", "key": "last_name", "name": "Last name", "description": null}
+                    ]
+                },
+                {"type": "boolean", "key": "is_active", "name": "Is active", "description": null},
+                {"type": "boolean", "key": "license", "name": "License", "description": null}
+            ])
+        );
+    }
+
+    #[test]
+    fn condition_on_a_rendered_variable_is_text() {
+        let inputs = infer_inputs("{{#if name}}{{name}}{{/if}}").unwrap();
+        assert_eq!(inputs.len(), 1);
+        assert!(inputs[0].downcast_ref::<TextInput>().is_some());
+    }
+}
@@ -0,0 +1,344 @@
+use super::{referenced_variables_with_partials, Reference, Segment, Usage};
+use crate::{inputs::*, scenario::Scenario};
+use anyhow::{Context, Result};
+use core::fmt;
+use std::borrow::Cow;
+
+/// A likely mistake in a scenario.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Lint {
+    /// The template reads a variable that no input provides.
+    UndeclaredVariable {
+        raw: String,
+        template: String,
+        position: Option<(usize, usize)>,
+    },
+    /// An input is never read by the template.
+    UnusedInput { key: String },
+    /// Several sibling inputs have the same key.
+    DuplicateKey { key: String },
+}
+
+impl fmt::Display for Lint {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Lint::UndeclaredVariable {
+                raw,
+                template,
+                position: Some((line, column)),
+            } => write!(
+                f,
+                "Undeclared variable '{}' in template '{}' at line {}, column {}.",
+                raw, template, line, column
+            ),
+            Lint::UndeclaredVariable {
+                raw,
+                template,
+                position: None,
+            } => write!(
+                f,
+                "Undeclared variable '{}' in template '{}'.",
+                raw, template
+            ),
+            Lint::UnusedInput { key } => {
+                write!(f, "Input '{}' is never used by the template.", key)
+            }
+            Lint::DuplicateKey { key } => write!(f, "Duplicate input key '{}'.", key),
+        }
+    }
+}
+
+/// Cross-references the variables read by the output templates of a
+/// scenario, and the partials they include, with the keys of its inputs.
+pub fn lint_scenario(scenario: &Scenario) -> Result<Vec<Lint>> {
+    let sources: Vec<(&str, Cow<str>)> = scenario
+        .templates
+        .iter()
+        .map(|template| (template.name.as_str(), template.template.source()))
+        .collect();
+    let partials: Vec<(&str, &str)> = sources
+        .iter()
+        .map(|(name, source)| (*name, source.as_ref()))
+        .collect();
+
+    let mut declared = vec![];
+    let mut lints = vec![];
+    declare_inputs(&scenario.inputs, &[], &mut declared, &mut lints);
+
+    // The outputs and the templates of their file names.
+    let mut analyzed: Vec<(String, Cow<str>)> = vec![];
+    for output in scenario.outputs() {
+        analyzed.push((output.name.clone(), output.template.source()));
+        if let Some(file_name) = &output.file_name {
+            analyzed.push((
+                format!("{} (file name)", output.name),
+                Cow::Borrowed(file_name),
+            ));
+        }
+    }
+
+    let mut references = vec![];
+    for (name, source) in &analyzed {
+        let template_references = referenced_variables_with_partials(source, &partials)
+            .with_context(|| format!("Failed to analyze the template '{}'", name))?;
+        for reference in template_references {
+            let lint = Lint::UndeclaredVariable {
+                raw: reference.raw.clone(),
+                template: reference.partial.as_ref().unwrap_or(name).clone(),
+                position: reference.position,
+            };
+            // Partials included by several outputs are only reported once.
+            if !declared.contains(&reference.path) && !lints.contains(&lint) {
+                lints.push(lint);
+            }
+            references.push(reference);
+        }
+    }
+
+    find_unused_inputs(&scenario.inputs, &[], &references, &mut lints);
+    Ok(lints)
+}
+
+fn input_path(base: &[Segment], input: &InputTypes) -> Vec<Segment> {
+    let mut path = base.to_vec();
+    path.extend(
+        input
+            .key()
+            .get_segments()
+            .map(|segment| Segment::Key(segment.to_owned())),
+    );
+    path
+}
+
+/// Collects the paths declared by `inputs`, including the elements of
+/// lists, and reports duplicate keys.
+fn declare_inputs(
+    inputs: &[InputTypes],
+    base: &[Segment],
+    declared: &mut Vec<Vec<Segment>>,
+    lints: &mut Vec<Lint>,
+) {
+    for (i, input) in inputs.iter().enumerate() {
+        let mut path = input_path(base, input);
+        if inputs[..i].iter().any(|other| other.key() == input.key()) {
+            lints.push(Lint::DuplicateKey {
+                key: display_path(&path),
+            });
+            continue;
+        }
+
+        // A dotted key like `license.name` also declares `license`.
+        for end in base.len() + 1..path.len() {
+            if !declared.iter().any(|other| other[..] == path[..end]) {
+                declared.push(path[..end].to_vec());
+            }
+        }
+        declared.push(path.clone());
+        if is_list(input) {
+            path.push(Segment::Item);
+            declared.push(path.clone());
+        }
+        declare_inputs(input.children(), &path, declared, lints);
+    }
+}
+
+/// Reports the inputs that are never read, without descending into the
+/// children of unused groups and lists.
+fn find_unused_inputs(
+    inputs: &[InputTypes],
+    base: &[Segment],
+    references: &[Reference],
+    lints: &mut Vec<Lint>,
+) {
+    for input in inputs {
+        let mut path = input_path(base, input);
+        let is_used = references.iter().any(|reference| {
+            reference.path.starts_with(&path)
+                || (reference.usage == Usage::Value && path.starts_with(&reference.path))
+        });
+
+        if !is_used {
+            lints.push(Lint::UnusedInput {
+                key: display_path(&path),
+            });
+        } else {
+            if is_list(input) {
+                path.push(Segment::Item);
+            }
+            find_unused_inputs(input.children(), &path, references, lints);
+        }
+    }
+}
+
+fn is_list(input: &InputTypes) -> bool {
+    input.downcast_ref::<ListInput>().is_some()
+}
+
+fn display_path(path: &[Segment]) -> String {
+    let segments: Vec<&str> = path
+        .iter()
+        .map(|segment| match segment {
+            Segment::Key(key) => key.as_str(),
+            Segment::Item => "[]",
+        })
+        .collect();
+    segments.join(".")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn lint(template: &str, inputs: serde_json::Value) -> Vec<String> {
+        let scenario: Scenario =
+            serde_json::from_value(json!({ "template": template, "inputs": inputs })).unwrap();
+        lint_scenario(&scenario)
+            .unwrap()
+            .iter()
+            .map(ToString::to_string)
+            .collect()
+    }
+
+    #[test]
+    fn debug_scenario_lints() {
+        let scenario = include_str!("../input_data.json").replace(
+            "%TEMPLATE%",
+            &include_str!("../input_template.hbs").replace("\n", "\\n"),
+        );
+        let scenario: Scenario = serde_json::from_str(&scenario).unwrap();
+        assert_eq!(
+            lint_scenario(&scenario).unwrap(),
+            vec![Lint::UnusedInput {
+                key: "author.useless_info".to_owned()
+            }]
+        );
+    }
+
+    #[test]
+    fn undeclared_variables() {
+        let inputs = json!([
+            {
+                "type": "group",
+                "key": "author",
+                "name": "Author",
+                "inputs": [{"type": "text", "key": "first_name", "name": "First name"}]
+            },
+            {
+                "type": "list",
+                "key": "persons",
+                "name": "Persons",
+                "inputs": [{"type": "text", "key": "name", "name": "Name"}]
+            }
+        ]);
+        assert_eq!(
+            lint(
+                "{{autor.first_name}}\n{{#with author}}{{first_name}}{{last_name}}{{/with}}\n\
+                 {{#each persons as |p|}}{{p.name}}\n  {{p.age}}{{/each}}",
+                inputs
+            ),
+            vec![
+                "Undeclared variable 'autor.first_name' in template 'main' at line 1, column 1.",
+                "Undeclared variable 'last_name' in template 'main' at line 2, column 31.",
+                "Undeclared variable 'p.age' in template 'main' at line 4, column 3.",
+            ]
+        );
+    }
+
+    #[test]
+    fn dotted_keys_declare_their_prefixes() {
+        let inputs = json!([
+            {"type": "text", "key": "license.name", "name": "License"},
+            {"type": "text", "key": "license.url", "name": "License URL"}
+        ]);
+        assert_eq!(
+            lint(
+                "{{#if license}}{{#with license}}{{name}} {{url}}{{/with}}{{/if}}",
+                inputs
+            ),
+            Vec::<String>::new()
+        );
+    }
+
+    #[test]
+    fn templates_and_partials() {
+        let scenario: Scenario = serde_json::from_value(json!({
+            "templates": [
+                {
+                    "name": "a.txt",
+                    "template": "{{> person author}}",
+                    "output": true,
+                    "file_name": "{{author.name}}-{{version}}.txt"
+                },
+                {"name": "b.txt", "template": "{{> person author}}\n{{> person editor}}", "output": true},
+                {"name": "person", "template": "{{name}} {{age}}"},
+                {"name": "unused", "template": "{{nothing}}"}
+            ],
+            "inputs": [
+                {
+                    "type": "group",
+                    "key": "author",
+                    "name": "Author",
+                    "inputs": [{"type": "text", "key": "name", "name": "Name"}]
+                },
+                {"type": "text", "key": "date", "name": "Date"}
+            ]
+        }))
+        .unwrap();
+        let lints: Vec<String> = lint_scenario(&scenario)
+            .unwrap()
+            .iter()
+            .map(ToString::to_string)
+            .collect();
+        assert_eq!(
+            lints,
+            vec![
+                "Undeclared variable 'age' in template 'person' at line 1, column 10.",
+                "Undeclared variable 'version' in template 'a.txt (file name)' at line 1, column 17.",
+                "Undeclared variable 'editor' in template 'b.txt' at line 2, column 1.",
+                "Undeclared variable 'name' in template 'person' at line 1, column 1.",
+                "Input 'date' is never used by the template.",
+            ]
+        );
+    }
+
+    #[test]
+    fn unused_inputs_and_duplicate_keys() {
+        let inputs = json!([
+            {"type": "text", "key": "a", "name": "A"},
+            {"type": "text", "key": "a", "name": "A again"},
+            {
+                "type": "group",
+                "key": "unused",
+                "name": "Unused",
+                "inputs": [{"type": "text", "key": "b", "name": "B"}]
+            },
+            {
+                "type": "group",
+                "key": "dumped",
+                "name": "Dumped",
+                "inputs": [{"type": "text", "key": "c", "name": "C"}]
+            },
+            {
+                "type": "list",
+                "key": "list",
+                "name": "List",
+                "inputs": [
+                    {"type": "text", "key": "d", "name": "D"},
+                    {"type": "boolean", "key": "e", "name": "E"}
+                ]
+            }
+        ]);
+        assert_eq!(
+            lint(
+                "{{a}}{{lookup dumped \"c\"}}{{#each list}}{{#if e}}{{/if}}{{/each}}",
+                inputs
+            ),
+            vec![
+                "Duplicate input key 'a'.",
+                "Input 'unused' is never used by the template.",
+                "Input 'list.[].d' is never used by the template.",
+            ]
+        );
+    }
+}
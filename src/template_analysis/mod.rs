@@ -0,0 +1,491 @@
+//! Static analysis of Handlebars templates: finds the variables a
+//! template reads, resolving `#each` / `#with` scopes and block params.
+
+mod infer;
+mod lint;
+
+pub use infer::infer_inputs;
+pub use lint::lint_scenario;
+
+use anyhow::{Context, Result};
+use handlebars::{
+    template::{
+        BlockParam, DecoratorTemplate, HelperTemplate, Parameter, Template, TemplateElement,
+    },
+    Path as HandlebarsPath,
+};
+use std::collections::HashMap;
+
+/// A segment of the absolute path of a variable.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Segment {
+    Key(String),
+    /// Any element of a list, either iterated by `#each` or accessed
+    /// with an index like `persons.[0]`.
+    Item,
+}
+
+/// How a variable is used by the template.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Usage {
+    /// Rendered, or passed to a helper.
+    Value,
+    /// Tested by `#if` or `#unless`.
+    Condition,
+    /// Iterated over by `#each`.
+    List,
+    /// Used as the context of `#with`.
+    Context,
+}
+
+/// A variable read by a template.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Reference {
+    pub path: Vec<Segment>,
+    /// The path as written in the template.
+    pub raw: String,
+    pub usage: Usage,
+    /// Line and column of the expression in the template, if known.
+    pub position: Option<(usize, usize)>,
+    /// The partial containing the expression, `None` if it is in the
+    /// analyzed template itself.
+    pub partial: Option<String>,
+}
+
+/// Parses the template and returns the variables it reads, in order of
+/// appearance. Local variables like `@index` are not reported.
+pub fn referenced_variables(source: &str) -> Result<Vec<Reference>> {
+    referenced_variables_with_partials(source, &[])
+}
+
+/// Same as [`referenced_variables`], but also follows the given partials,
+/// as `(name, source)`, where the template includes them with `{{> name}}`.
+pub fn referenced_variables_with_partials(
+    source: &str,
+    partials: &[(&str, &str)],
+) -> Result<Vec<Reference>> {
+    let template = Template::compile2(source, true).context("Failed to parse the template")?;
+    let partials = partials
+        .iter()
+        .map(|(name, source)| {
+            let partial = Template::compile2(source, true)
+                .with_context(|| format!("Failed to parse the partial '{}'", name))?;
+            Ok((name.to_string(), partial))
+        })
+        .collect::<Result<_>>()?;
+
+    let mut collector = Collector {
+        scopes: vec![Scope {
+            base: Some(vec![]),
+            block_params: vec![],
+        }],
+        references: vec![],
+        partials: &partials,
+        partial_stack: vec![],
+    };
+    collector.visit_template(&template);
+    Ok(collector.references)
+}
+
+struct Scope {
+    /// Absolute path of the context, `None` if it can't be resolved
+    /// statically (eg. `#with` over a helper's result).
+    base: Option<Vec<Segment>>,
+    /// Block params introduced with `as |name|`, with the path they are
+    /// bound to, `None` for indices and keys.
+    block_params: Vec<(String, Option<Vec<Segment>>)>,
+}
+
+struct Collector<'a> {
+    scopes: Vec<Scope>,
+    references: Vec<Reference>,
+    partials: &'a HashMap<String, Template>,
+    /// The partials being visited, the innermost last.
+    partial_stack: Vec<&'a str>,
+}
+
+impl<'a> Collector<'a> {
+    fn visit_template(&mut self, template: &Template) {
+        for (i, element) in template.elements.iter().enumerate() {
+            let position = template
+                .mapping
+                .as_ref()
+                .and_then(|mapping| mapping.get(i))
+                .map(|mapping| (mapping.0, mapping.1));
+            self.visit_element(element, position);
+        }
+    }
+
+    fn visit_element(&mut self, element: &TemplateElement, position: Option<(usize, usize)>) {
+        match element {
+            TemplateElement::RawString(_) | TemplateElement::Comment(_) => {}
+            TemplateElement::HTMLExpression(param) => {
+                self.visit_parameter(param, Usage::Value, position)
+            }
+            TemplateElement::Expression(helper) => {
+                if helper.params.is_empty() && helper.hash.is_empty() {
+                    self.visit_parameter(&helper.name, Usage::Value, position);
+                } else {
+                    self.visit_helper_params(helper, Usage::Value, position);
+                }
+            }
+            TemplateElement::HelperBlock(helper) => self.visit_block(helper, position),
+            TemplateElement::DecoratorExpression(decorator)
+            | TemplateElement::DecoratorBlock(decorator) => {
+                for param in decorator.params.iter().chain(decorator.hash.values()) {
+                    self.visit_parameter(param, Usage::Value, position);
+                }
+                if let Some(template) = &decorator.template {
+                    self.visit_template(template);
+                }
+            }
+            TemplateElement::PartialExpression(partial)
+            | TemplateElement::PartialBlock(partial) => self.visit_partial(partial, position),
+        }
+    }
+
+    /// Visits the parameters of a partial, then the partial itself with
+    /// its context: the first parameter if any, with the hash parameters
+    /// as local variables. Recursive partials are only visited once.
+    fn visit_partial(&mut self, partial: &DecoratorTemplate, position: Option<(usize, usize)>) {
+        for param in partial.params.iter().chain(partial.hash.values()) {
+            self.visit_parameter(param, Usage::Value, position);
+        }
+        if let Some(template) = &partial.template {
+            self.visit_template(template);
+        }
+
+        let partials = self.partials;
+        let (name, template) = match partial
+            .name
+            .as_name()
+            .and_then(|name| partials.get_key_value(name))
+        {
+            Some(found) => found,
+            None => return,
+        };
+        if self.partial_stack.contains(&name.as_str()) {
+            return;
+        }
+
+        let base = match partial.params.first() {
+            Some(Parameter::Path(path)) => self.resolve(path),
+            Some(_) => None,
+            None => self.scopes.last().and_then(|scope| scope.base.clone()),
+        };
+        let block_params = partial
+            .hash
+            .iter()
+            .map(|(name, param)| {
+                let path = match param {
+                    Parameter::Path(path) => self.resolve(path),
+                    _ => None,
+                };
+                (name.clone(), path)
+            })
+            .collect();
+
+        self.scopes.push(Scope { base, block_params });
+        self.partial_stack.push(name);
+        self.visit_template(template);
+        self.partial_stack.pop();
+        self.scopes.pop();
+    }
+
+    fn visit_block(&mut self, helper: &HelperTemplate, position: Option<(usize, usize)>) {
+        let target = helper.params.first();
+        let scoped_usage = match helper.name.as_name() {
+            Some("each") => Some(Usage::List),
+            Some("with") => Some(Usage::Context),
+            Some("if") | Some("unless") => {
+                self.visit_helper_params(helper, Usage::Condition, position);
+                None
+            }
+            _ => {
+                self.visit_helper_params(helper, Usage::Value, position);
+                None
+            }
+        };
+
+        match scoped_usage {
+            Some(usage) => {
+                if let Some(param) = target {
+                    self.visit_parameter(param, usage, position);
+                }
+                for param in helper.params.iter().skip(1).chain(helper.hash.values()) {
+                    self.visit_parameter(param, Usage::Value, position);
+                }
+
+                let target = match target {
+                    Some(Parameter::Path(path)) => self.resolve(path),
+                    _ => None,
+                };
+                let base = match (usage, target) {
+                    (Usage::List, Some(mut path)) => {
+                        path.push(Segment::Item);
+                        Some(path)
+                    }
+                    (_, target) => target,
+                };
+                let block_params = match &helper.block_param {
+                    Some(BlockParam::Single(name)) => vec![(name, base.clone())],
+                    Some(BlockParam::Pair((name, index))) => {
+                        vec![(name, base.clone()), (index, None)]
+                    }
+                    None => vec![],
+                }
+                .into_iter()
+                .filter_map(|(name, path)| name.as_name().map(|name| (name.to_owned(), path)))
+                .collect();
+
+                self.scopes.push(Scope { base, block_params });
+                if let Some(template) = &helper.template {
+                    self.visit_template(template);
+                }
+                self.scopes.pop();
+            }
+            None => {
+                if let Some(template) = &helper.template {
+                    self.visit_template(template);
+                }
+            }
+        }
+
+        if let Some(inverse) = &helper.inverse {
+            self.visit_template(inverse);
+        }
+    }
+
+    fn visit_helper_params(
+        &mut self,
+        helper: &HelperTemplate,
+        usage: Usage,
+        position: Option<(usize, usize)>,
+    ) {
+        for param in helper.params.iter().chain(helper.hash.values()) {
+            self.visit_parameter(param, usage, position);
+        }
+    }
+
+    fn visit_parameter(
+        &mut self,
+        param: &Parameter,
+        usage: Usage,
+        position: Option<(usize, usize)>,
+    ) {
+        match param {
+            Parameter::Path(path) => {
+                if let (HandlebarsPath::Relative((_, raw)), Some(resolved)) =
+                    (path, self.resolve(path))
+                {
+                    if !resolved.is_empty() {
+                        self.references.push(Reference {
+                            path: resolved,
+                            raw: raw.clone(),
+                            usage,
+                            position,
+                            partial: self.partial_stack.last().map(|name| name.to_string()),
+                        });
+                    }
+                }
+            }
+            Parameter::Subexpression(subexpression) => {
+                self.visit_element(subexpression.as_element(), position)
+            }
+            Parameter::Name(_) | Parameter::Literal(_) => {}
+        }
+    }
+
+    /// Returns the absolute path of a template path, or `None` if it is
+    /// a local variable or can't be resolved statically.
+    fn resolve(&self, path: &HandlebarsPath) -> Option<Vec<Segment>> {
+        let raw = match path {
+            HandlebarsPath::Relative((_, raw)) => RawPath::parse(raw),
+            HandlebarsPath::Local(_) => return None,
+        };
+
+        let mut segments = raw.segments.iter();
+        let mut resolved = if raw.root {
+            vec![]
+        } else {
+            let scopes = &self.scopes[..self.scopes.len().checked_sub(raw.up)?];
+            let block_param = match raw.segments.first() {
+                Some(first) => scopes.iter().rev().find_map(|scope| {
+                    scope
+                        .block_params
+                        .iter()
+                        .find(|(name, _)| name == first)
+                        .map(|(_, path)| path)
+                }),
+                None => None,
+            };
+            match block_param {
+                Some(bound) => {
+                    segments.next();
+                    bound.clone()?
+                }
+                None => scopes.last()?.base.clone()?,
+            }
+        };
+
+        resolved.extend(segments.map(|segment| match segment.parse::<usize>() {
+            Ok(_) => Segment::Item,
+            Err(_) => Segment::Key(segment.clone()),
+        }));
+        Some(resolved)
+    }
+}
+
+/// A template path split into its components, eg. `../persons.[0].name`.
+struct RawPath {
+    root: bool,
+    up: usize,
+    segments: Vec<String>,
+}
+
+impl RawPath {
+    fn parse(raw: &str) -> Self {
+        let mut rest = raw;
+        for prefix in &["this.", "this/", "./"] {
+            if rest.starts_with(prefix) {
+                rest = &rest[prefix.len()..];
+            }
+        }
+        if rest == "this" {
+            rest = "";
+        }
+
+        let root = rest.starts_with("@root");
+        if root {
+            rest = rest["@root".len()..].trim_start_matches(['.', '/']);
+        }
+
+        let mut up = 0;
+        while rest.starts_with("../") || rest == ".." {
+            up += 1;
+            rest = rest.get(3..).unwrap_or("");
+        }
+
+        let mut segments = vec![];
+        while !rest.is_empty() {
+            if rest.starts_with('[') {
+                let end = rest.find(']').unwrap_or(rest.len());
+                segments.push(rest[1..end].to_owned());
+                rest = rest.get(end + 1..).unwrap_or("");
+            } else {
+                let end = rest.find(['.', '/', '[']).unwrap_or(rest.len());
+                if &rest[..end] != "this" {
+                    segments.push(rest[..end].to_owned());
+                }
+                rest = &rest[end..];
+            }
+            rest = rest.trim_start_matches(['.', '/']);
+        }
+
+        Self { root, up, segments }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn joined(path: &[Segment]) -> String {
+        let segments: Vec<_> = path
+            .iter()
+            .map(|segment| match segment {
+                Segment::Key(key) => key.as_str(),
+                Segment::Item => "[]",
+            })
+            .collect();
+        segments.join(".")
+    }
+
+    fn paths(source: &str) -> Vec<(String, Usage)> {
+        referenced_variables(source)
+            .unwrap()
+            .into_iter()
+            .map(|reference| (joined(&reference.path), reference.usage))
+            .collect()
+    }
+
+    #[test]
+    fn simple_paths() {
+        assert_eq!(
+            paths("{{a}} {{{b.c}}} {{d.[0].e}} {{this.f}} {{@root.g}} {{lookup h 1}}"),
+            vec![
+                ("a".to_owned(), Usage::Value),
+                ("b.c".to_owned(), Usage::Value),
+                ("d.[].e".to_owned(), Usage::Value),
+                ("f".to_owned(), Usage::Value),
+                ("g".to_owned(), Usage::Value),
+                ("h".to_owned(), Usage::Value),
+            ]
+        );
+    }
+
+    #[test]
+    fn scoped_blocks() {
+        let source = "{{#with author}}{{name}}{{../date}}{{/with}}\
+                      {{#each persons as |person i|}}{{person.name}}{{i}}{{@index}}{{/each}}\
+                      {{#each tags}}{{this}}{{else}}{{empty}}{{/each}}\
+                      {{#if (eq a.b 1)}}{{#unless c}}{{/unless}}{{/if}}";
+        assert_eq!(
+            paths(source),
+            vec![
+                ("author".to_owned(), Usage::Context),
+                ("author.name".to_owned(), Usage::Value),
+                ("date".to_owned(), Usage::Value),
+                ("persons".to_owned(), Usage::List),
+                ("persons.[].name".to_owned(), Usage::Value),
+                ("tags".to_owned(), Usage::List),
+                ("tags.[]".to_owned(), Usage::Value),
+                ("empty".to_owned(), Usage::Value),
+                ("a.b".to_owned(), Usage::Value),
+                ("c".to_owned(), Usage::Condition),
+            ]
+        );
+    }
+
+    #[test]
+    fn positions() {
+        let references = referenced_variables("a\n  {{b}}\n{{#each c}}{{d}}{{/each}}").unwrap();
+        let positions: Vec<_> = references.iter().map(|r| r.position).collect();
+        assert_eq!(positions, vec![Some((2, 3)), Some((3, 1)), Some((3, 12))]);
+    }
+
+    #[test]
+    fn partials() {
+        let partials = [
+            ("person", "{{name}}{{> address}}"),
+            ("address", "{{city}}{{#if nested}}{{> person}}{{/if}}"),
+            ("tag", "{{t}}"),
+        ];
+        let references = referenced_variables_with_partials(
+            "{{> person author}}{{#each persons}}{{> person}}{{/each}}\
+             {{> tag t=tags.[0]}}{{> unknown missing}}",
+            &partials,
+        )
+        .unwrap();
+        let found: Vec<_> = references
+            .iter()
+            .map(|reference| (joined(&reference.path), reference.partial.as_deref()))
+            .collect();
+        assert_eq!(
+            found,
+            vec![
+                ("author".to_owned(), None),
+                ("author.name".to_owned(), Some("person")),
+                ("author.city".to_owned(), Some("address")),
+                ("author.nested".to_owned(), Some("address")),
+                ("persons".to_owned(), None),
+                ("persons.[].name".to_owned(), Some("person")),
+                ("persons.[].city".to_owned(), Some("address")),
+                ("persons.[].nested".to_owned(), Some("address")),
+                ("tags.[]".to_owned(), None),
+                ("tags.[]".to_owned(), Some("tag")),
+                ("missing".to_owned(), None),
+            ]
+        );
+    }
+}
@@ -0,0 +1,159 @@
+use core::fmt;
+use handlebars::RenderError;
+use serde::{Deserialize, Serialize};
+
+/// A template which failed to compile or to render, with the position of
+/// the error when the template engine knows it.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct TemplateError {
+    pub template: Option<String>,
+    /// Line and column, both starting at 1.
+    pub position: Option<(usize, usize)>,
+    pub reason: String,
+}
+
+impl TemplateError {
+    /// Shows the line of `source` where the error is, see [`excerpt`].
+    pub fn excerpt(&self, source: &str) -> Option<String> {
+        excerpt(source, self.position?)
+    }
+}
+
+/// Shows the line of `source` at a line and column starting at 1, with a
+/// caret under the column.
+pub fn excerpt(source: &str, (line, column): (usize, usize)) -> Option<String> {
+    let content = source.lines().nth(line.checked_sub(1)?)?;
+    let gutter = format!("{} | ", line);
+    // Tabs are kept in the margin so that the caret stays aligned.
+    let margin: String = content
+        .chars()
+        .take(column.saturating_sub(1))
+        .map(|c| if c == '\t' { '\t' } else { ' ' })
+        .collect();
+    Some(format!(
+        "{}{}\n{:width$}{}^",
+        gutter,
+        content,
+        "",
+        margin,
+        width = gutter.len()
+    ))
+}
+
+impl fmt::Display for TemplateError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.reason)?;
+        if let Some(template) = &self.template {
+            write!(f, " in template '{}'", template)?;
+        }
+        if let Some((line, column)) = self.position {
+            write!(f, " at line {}, column {}", line, column)?;
+        }
+        write!(f, ".")
+    }
+}
+
+impl std::error::Error for TemplateError {}
+
+impl From<handlebars::TemplateError> for TemplateError {
+    fn from(error: handlebars::TemplateError) -> Self {
+        Self {
+            reason: capitalize(error.reason.to_string().trim_end_matches('.')),
+            position: error.line_no.zip(error.column_no),
+            template: error.template_name,
+        }
+    }
+}
+
+impl From<RenderError> for TemplateError {
+    /// Names the missing variable for the errors of the strict mode.
+    fn from(error: RenderError) -> Self {
+        let reason = match missing_variable(&error.desc) {
+            Some(missing_variable) => format!("Missing variable '{}'", missing_variable),
+            None => error.desc.trim_end_matches('.').to_owned(),
+        };
+        Self {
+            reason,
+            position: error.line_no.zip(error.column_no),
+            template: error.template_name,
+        }
+    }
+}
+
+/// Finds the variable of an error of the strict mode. Handlebars 3.5 only
+/// describes it in the message, whose shape is taken from the constructor
+/// of these errors.
+fn missing_variable(desc: &str) -> Option<String> {
+    const MARKER: &str = "MISSING";
+    let message = RenderError::strict_error(Some(&MARKER.to_owned())).desc;
+    let (prefix, suffix) = message.split_once(&format!("{:?}", MARKER))?;
+    let quoted = desc.strip_prefix(prefix)?.strip_suffix(suffix)?;
+    serde_json::from_str(quoted).ok()
+}
+
+pub(crate) fn capitalize(reason: &str) -> String {
+    let mut chars = reason.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use handlebars::Handlebars;
+
+    #[test]
+    fn compile_error() {
+        let error: TemplateError = Handlebars::new()
+            .register_template_string("main", "Hello\n  {{#if a}}{{/each}}")
+            .unwrap_err()
+            .into();
+        assert_eq!(error.template.as_deref(), Some("main"));
+        assert!(error.position.is_some());
+        assert!(error.to_string().starts_with("Helper \"if\" was opened"));
+    }
+
+    /// Fails when an update of handlebars changes its errors of the strict
+    /// mode, see [`missing_variable`].
+    #[test]
+    fn missing_variable_in_strict_mode() {
+        let mut handlebars = Handlebars::new();
+        handlebars.set_strict_mode(true);
+        handlebars
+            .register_template_string("main", "\n {{a.b}}")
+            .unwrap();
+        let error: TemplateError = handlebars
+            .render("main", &serde_json::json!({"a": {}}))
+            .unwrap_err()
+            .into();
+        assert_eq!(
+            error.to_string(),
+            "Missing variable 'a.b' in template 'main' at line 2, column 2."
+        );
+    }
+
+    #[test]
+    fn excerpt_with_a_caret() {
+        let error = TemplateError {
+            template: Some("main".to_owned()),
+            position: Some((2, 4)),
+            reason: "Missing variable 'b'".to_owned(),
+        };
+        assert_eq!(
+            error.to_string(),
+            "Missing variable 'b' in template 'main' at line 2, column 4."
+        );
+        assert_eq!(
+            error.excerpt("{{a}}\n\t x{{b}}\n").unwrap(),
+            "2 | \t x{{b}}\n    \t  ^"
+        );
+        assert_eq!(error.excerpt("{{a}}"), None);
+        let unknown = TemplateError {
+            position: None,
+            ..error
+        };
+        assert_eq!(unknown.excerpt("{{a}}\n{{b}}"), None);
+    }
+}
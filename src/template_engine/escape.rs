@@ -0,0 +1,158 @@
+//! Escaping of the values rendered by `{{...}}`, depending on the kind of
+//! file generated. `{{{...}}}` never escapes.
+
+use handlebars::{
+    html_escape, Context, Handlebars, Helper, HelperDef, HelperResult, JsonRender, Output,
+    RenderContext, RenderError,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::Value as JsonValue;
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum Escape {
+    None,
+    Html,
+    /// Escapes the content of a JSON string, eg. `"{{name}}"`.
+    Json,
+    /// Quotes values as a single POSIX shell word, eg. `echo {{name}}`.
+    Shell,
+    /// Quotes values as SQL string literals, eg. `WHERE name = {{name}}`.
+    Sql,
+}
+
+impl Default for Escape {
+    /// Handlebars' default.
+    fn default() -> Self {
+        Escape::Html
+    }
+}
+
+impl Escape {
+    pub fn is_default(&self) -> bool {
+        *self == Escape::default()
+    }
+
+    pub fn escape(self, value: &str) -> String {
+        match self {
+            Escape::None => value.to_owned(),
+            Escape::Html => html_escape(value),
+            Escape::Json => escape_json(value),
+            Escape::Shell => format!("'{}'", value.replace('\'', r"'\''")),
+            Escape::Sql => format!("'{}'", value.replace('\'', "''")),
+        }
+    }
+}
+
+fn escape_json(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            '\t' => escaped.push_str("\\t"),
+            c if (c as u32) < 0x20 => escaped.push_str(&format!("\\u{:04x}", c as u32)),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// `{{escape "shell" value}}` escapes a value differently than the rest of
+/// the scenario. Its output isn't escaped again.
+pub struct EscapeHelper;
+
+impl HelperDef for EscapeHelper {
+    fn call<'reg: 'rc, 'rc>(
+        &self,
+        h: &Helper<'reg, 'rc>,
+        _: &'reg Handlebars,
+        _: &'rc Context,
+        _: &mut RenderContext<'reg, 'rc>,
+        out: &mut dyn Output,
+    ) -> HelperResult {
+        let escape = h
+            .param(0)
+            .and_then(|mode| serde_json::from_value::<Escape>(mode.value().clone()).ok())
+            .ok_or_else(|| {
+                RenderError::new(
+                    "`escape` helper: the first parameter must be one of \"none\", \"html\", \
+                     \"json\", \"shell\" or \"sql\".",
+                )
+            })?;
+        let value = h
+            .param(1)
+            .map(|value| value.value())
+            .unwrap_or(&JsonValue::Null);
+        out.write(&escape.escape(&value.render()))?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        scenario::{Scenario, MAIN_TEMPLATE_NAME},
+        template_engine::{HandlebarsEngine, TemplateEngine},
+    };
+    use serde_json::json;
+
+    #[test]
+    fn escapers() {
+        let value = "it's \"<b>\"\n\\";
+        let cases = [
+            (Escape::None, "it's \"<b>\"\n\\"),
+            (Escape::Html, "it&#x27;s &quot;&lt;b&gt;&quot;\n\\"),
+            (Escape::Json, "it's \\\"<b>\\\"\\n\\\\"),
+            (Escape::Shell, "'it'\\''s \"<b>\"\n\\'"),
+            (Escape::Sql, "'it''s \"<b>\"\n\\'"),
+        ];
+        for (escape, expected) in &cases {
+            assert_eq!(escape.escape(value), *expected, "{:?}", escape);
+        }
+        assert_eq!(Escape::Json.escape("\u{1}é"), "\\u0001é");
+        assert_eq!(Escape::Shell.escape(""), "''");
+    }
+
+    fn render(escape: &str, template: &str) -> anyhow::Result<String> {
+        let scenario: Scenario = serde_json::from_value(json!({
+            "template": template,
+            "escape": escape,
+            "inputs": []
+        }))
+        .unwrap();
+        let engine = HandlebarsEngine::with_scenario(&scenario).unwrap();
+        engine.render(MAIN_TEMPLATE_NAME, &json!({"name": "O'Brien & co"}))
+    }
+
+    #[test]
+    fn scenario_escaping() {
+        assert_eq!(render("none", "{{name}}").unwrap(), "O'Brien & co");
+        assert_eq!(render("html", "{{name}}").unwrap(), "O&#x27;Brien &amp; co");
+        assert_eq!(
+            render("sql", "WHERE name = {{name}} -- {{{name}}}").unwrap(),
+            "WHERE name = 'O''Brien & co' -- O'Brien & co"
+        );
+        // Helpers' results are escaped too.
+        assert_eq!(
+            render("shell", "echo {{replace name \"&\" \"and\"}}").unwrap(),
+            "echo 'O'\\''Brien and co'"
+        );
+    }
+
+    #[test]
+    fn escape_helper() {
+        assert_eq!(
+            render("none", "{{escape \"json\" name}} {{escape \"sql\" name}}").unwrap(),
+            "O'Brien & co 'O''Brien & co'"
+        );
+        assert_eq!(
+            render("html", "{{escape \"none\" name}}").unwrap(),
+            "O'Brien & co"
+        );
+        assert!(render("none", "{{escape \"xml\" name}}").is_err());
+    }
+}
@@ -0,0 +1,612 @@
+//! Helpers for code generation, registered on every [`HandlebarsEngine`].
+//!
+//! They all compute a value, so they can be used in subexpressions, eg.
+//! `{{#if (gt (add a b) 10)}}`.
+//!
+//! [`HandlebarsEngine`]: super::HandlebarsEngine
+
+use handlebars::{Context, Handlebars, Helper, HelperDef, RenderContext, RenderError, ScopedJson};
+use serde_json::Value as JsonValue;
+use std::cmp::Ordering;
+
+type HelperFn = fn(&Helper) -> Result<JsonValue, RenderError>;
+
+/// Registers all the helpers, replacing the stock comparison helpers which
+/// only support integers.
+pub fn register_helpers(handlebars: &mut Handlebars) {
+    let helpers: &[(&str, HelperFn)] = &[
+        ("snake_case", snake_case),
+        ("camel_case", camel_case),
+        ("pascal_case", pascal_case),
+        ("kebab_case", kebab_case),
+        ("screaming_snake_case", screaming_snake_case),
+        ("pluralize", pluralize),
+        ("indent", indent),
+        ("dedent", dedent),
+        ("join", join),
+        ("default", default),
+        ("replace", replace),
+        ("pad_left", pad_left),
+        ("pad_right", pad_right),
+        ("add", add),
+        ("sub", sub),
+        ("mul", mul),
+        ("div", div),
+        ("rem", rem),
+        ("gt", gt),
+        ("gte", gte),
+        ("lt", lt),
+        ("lte", lte),
+    ];
+    for (name, helper) in helpers {
+        handlebars.register_helper(name, Box::new(ValueHelper(*helper)));
+    }
+}
+
+struct ValueHelper(HelperFn);
+
+impl HelperDef for ValueHelper {
+    fn call_inner<'reg: 'rc, 'rc>(
+        &self,
+        h: &Helper<'reg, 'rc>,
+        _: &'reg Handlebars,
+        _: &'rc Context,
+        _: &mut RenderContext<'reg, 'rc>,
+    ) -> Result<Option<ScopedJson<'reg, 'rc>>, RenderError> {
+        (self.0)(h).map(|value| Some(ScopedJson::Derived(value)))
+    }
+}
+
+fn error(h: &Helper, message: &str) -> RenderError {
+    RenderError::new(format!("`{}` helper: {}", h.name(), message))
+}
+
+fn param<'a>(h: &'a Helper, index: usize) -> Result<&'a JsonValue, RenderError> {
+    h.param(index)
+        .map(|param| param.value())
+        .ok_or_else(|| error(h, &format!("missing parameter {}.", index + 1)))
+}
+
+fn str_param<'a>(h: &'a Helper, index: usize) -> Result<&'a str, RenderError> {
+    param(h, index)?
+        .as_str()
+        .ok_or_else(|| error(h, &format!("parameter {} must be a string.", index + 1)))
+}
+
+/// The largest width of `indent`, `pad_left` and `pad_right`, above which
+/// a typo could exhaust the memory.
+const MAX_WIDTH: u64 = 1000;
+
+/// Reads the width of `indent`, `pad_left` and `pad_right`.
+fn width_param(h: &Helper, index: usize) -> Result<usize, RenderError> {
+    let width = param(h, index)?.as_u64().ok_or_else(|| {
+        error(
+            h,
+            &format!("parameter {} must be a positive integer.", index + 1),
+        )
+    })?;
+    if width > MAX_WIDTH {
+        return Err(error(
+            h,
+            &format!(
+                "parameter {} must be at most {}, not {}.",
+                index + 1,
+                MAX_WIDTH,
+                width
+            ),
+        ));
+    }
+    Ok(width as usize)
+}
+
+/// Reads the optional padding character of `pad_left` and `pad_right`.
+fn char_param(h: &Helper, index: usize) -> Result<char, RenderError> {
+    if h.param(index).is_none() {
+        return Ok(' ');
+    }
+    let s = str_param(h, index)?;
+    let mut chars = s.chars();
+    match (chars.next(), chars.next()) {
+        (Some(c), None) => Ok(c),
+        _ => Err(error(h, "the padding must be a single character.")),
+    }
+}
+
+/// Splits an identifier into lowercase words, on non-alphanumeric
+/// characters and case changes, eg. `HTTPServer_v2` gives `http`,
+/// `server` and `v2`.
+fn words(s: &str) -> Vec<String> {
+    let chars: Vec<char> = s.chars().collect();
+    let mut words = vec![];
+    let mut word = String::new();
+
+    for (i, &c) in chars.iter().enumerate() {
+        if !c.is_alphanumeric() {
+            if !word.is_empty() {
+                words.push(std::mem::take(&mut word));
+            }
+            continue;
+        }
+
+        let previous = if i > 0 { Some(chars[i - 1]) } else { None };
+        let next = chars.get(i + 1);
+        let is_boundary = match previous {
+            Some(p) if p.is_alphanumeric() && c.is_uppercase() => {
+                !p.is_uppercase() || matches!(next, Some(n) if n.is_lowercase())
+            }
+            _ => false,
+        };
+        if is_boundary && !word.is_empty() {
+            words.push(std::mem::take(&mut word));
+        }
+        word.extend(c.to_lowercase());
+    }
+
+    if !word.is_empty() {
+        words.push(word);
+    }
+    words
+}
+
+fn capitalize(word: &str) -> String {
+    let mut chars = word.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
+fn snake_case(h: &Helper) -> Result<JsonValue, RenderError> {
+    Ok(words(str_param(h, 0)?).join("_").into())
+}
+
+fn camel_case(h: &Helper) -> Result<JsonValue, RenderError> {
+    let words = words(str_param(h, 0)?);
+    let mut camel = String::new();
+    for (i, word) in words.iter().enumerate() {
+        if i == 0 {
+            camel.push_str(word);
+        } else {
+            camel.push_str(&capitalize(word));
+        }
+    }
+    Ok(camel.into())
+}
+
+fn pascal_case(h: &Helper) -> Result<JsonValue, RenderError> {
+    let words = words(str_param(h, 0)?);
+    Ok(words
+        .iter()
+        .map(|word| capitalize(word))
+        .collect::<String>()
+        .into())
+}
+
+fn kebab_case(h: &Helper) -> Result<JsonValue, RenderError> {
+    Ok(words(str_param(h, 0)?).join("-").into())
+}
+
+fn screaming_snake_case(h: &Helper) -> Result<JsonValue, RenderError> {
+    Ok(words(str_param(h, 0)?).join("_").to_uppercase().into())
+}
+
+/// `{{pluralize word}}`, or `{{pluralize word count}}` which keeps the
+/// word singular when the count is 1. Only handles regular English nouns.
+fn pluralize(h: &Helper) -> Result<JsonValue, RenderError> {
+    let word = str_param(h, 0)?;
+    if let Some(count) = h.param(1) {
+        if count.value().as_f64() == Some(1.) {
+            return Ok(word.into());
+        }
+    }
+
+    let lowercase = word.to_lowercase();
+    let before_last = lowercase.chars().rev().nth(1);
+    let (stem, suffix) = if ["s", "x", "z", "ch", "sh"]
+        .iter()
+        .any(|end| lowercase.ends_with(end))
+    {
+        (word, "es")
+    } else if lowercase.ends_with('y') && matches!(before_last, Some(c) if !"aeiou".contains(c)) {
+        (&word[..word.len() - 1], "ies")
+    } else {
+        (word, "s")
+    };
+
+    let is_uppercase =
+        word.chars().any(char::is_alphabetic) && word.chars().all(|c| !c.is_lowercase());
+    if is_uppercase {
+        Ok(format!("{}{}", stem, suffix.to_uppercase()).into())
+    } else {
+        Ok(format!("{}{}", stem, suffix).into())
+    }
+}
+
+/// `{{indent text width}}` indents all the non-empty lines.
+fn indent(h: &Helper) -> Result<JsonValue, RenderError> {
+    let text = str_param(h, 0)?;
+    let indentation = " ".repeat(width_param(h, 1)?);
+    let lines: Vec<String> = text
+        .split('\n')
+        .map(|line| {
+            if line.trim().is_empty() {
+                line.to_owned()
+            } else {
+                format!("{}{}", indentation, line)
+            }
+        })
+        .collect();
+    Ok(lines.join("\n").into())
+}
+
+/// `{{dedent text}}` removes the indentation common to all the non-empty
+/// lines.
+fn dedent(h: &Helper) -> Result<JsonValue, RenderError> {
+    let text = str_param(h, 0)?;
+    let common = text
+        .split('\n')
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| line.len() - line.trim_start().len())
+        .min()
+        .unwrap_or(0);
+    let lines: Vec<&str> = text
+        .split('\n')
+        .map(|line| line.get(common..).unwrap_or_else(|| line.trim_start()))
+        .collect();
+    Ok(lines.join("\n").into())
+}
+
+/// `{{join list separator}}`.
+fn join(h: &Helper) -> Result<JsonValue, RenderError> {
+    let list = param(h, 0)?
+        .as_array()
+        .ok_or_else(|| error(h, "parameter 1 must be a list."))?;
+    let separator = str_param(h, 1)?;
+    let items: Vec<String> = list
+        .iter()
+        .map(|item| match item {
+            JsonValue::String(s) => s.clone(),
+            other => other.to_string(),
+        })
+        .collect();
+    Ok(items.join(separator).into())
+}
+
+/// `{{default value fallback}}` gives the fallback when the value is
+/// missing, null or an empty string.
+fn default(h: &Helper) -> Result<JsonValue, RenderError> {
+    let value = param(h, 0)?;
+    match value {
+        JsonValue::Null => Ok(param(h, 1)?.clone()),
+        JsonValue::String(s) if s.is_empty() => Ok(param(h, 1)?.clone()),
+        _ => Ok(value.clone()),
+    }
+}
+
+/// `{{replace text from to}}` replaces all the occurrences of `from`.
+fn replace(h: &Helper) -> Result<JsonValue, RenderError> {
+    let text = str_param(h, 0)?;
+    let from = str_param(h, 1)?;
+    let to = str_param(h, 2)?;
+    Ok(text.replace(from, to).into())
+}
+
+/// `{{pad_left text width}}`, with an optional padding character as third
+/// parameter, a space by default.
+fn pad_left(h: &Helper) -> Result<JsonValue, RenderError> {
+    let (text, padding) = padding(h)?;
+    Ok(format!("{}{}", padding, text).into())
+}
+
+/// Same as [`pad_left`], but pads on the right.
+fn pad_right(h: &Helper) -> Result<JsonValue, RenderError> {
+    let (text, padding) = padding(h)?;
+    Ok(format!("{}{}", text, padding).into())
+}
+
+fn padding<'a>(h: &'a Helper) -> Result<(&'a str, String), RenderError> {
+    let text = str_param(h, 0)?;
+    let width = width_param(h, 1)?;
+    let pad = char_param(h, 2)?;
+    let missing = width.saturating_sub(text.chars().count());
+    Ok((text, pad.to_string().repeat(missing)))
+}
+
+enum Number {
+    Integer(i64),
+    Float(f64),
+}
+
+impl Number {
+    fn as_f64(&self) -> f64 {
+        match *self {
+            Number::Integer(i) => i as f64,
+            Number::Float(f) => f,
+        }
+    }
+}
+
+fn number_param(h: &Helper, index: usize) -> Result<Number, RenderError> {
+    let value = param(h, index)?;
+    match (value.as_i64(), value.as_f64()) {
+        (Some(i), _) => Ok(Number::Integer(i)),
+        (None, Some(f)) => Ok(Number::Float(f)),
+        _ => Err(error(
+            h,
+            &format!("parameter {} must be a number.", index + 1),
+        )),
+    }
+}
+
+/// Applies an operation on integers when both operands are integers,
+/// falling back on floats otherwise.
+fn arithmetic(
+    h: &Helper,
+    integers: fn(i64, i64) -> Option<i64>,
+    floats: fn(f64, f64) -> f64,
+) -> Result<JsonValue, RenderError> {
+    match (number_param(h, 0)?, number_param(h, 1)?) {
+        (Number::Integer(a), Number::Integer(b)) => integers(a, b)
+            .map(JsonValue::from)
+            .ok_or_else(|| error(h, "overflow or division by zero.")),
+        (a, b) => {
+            let result = floats(a.as_f64(), b.as_f64());
+            if result.is_finite() {
+                Ok(result.into())
+            } else {
+                Err(error(h, "the result isn't a finite number."))
+            }
+        }
+    }
+}
+
+fn add(h: &Helper) -> Result<JsonValue, RenderError> {
+    arithmetic(h, i64::checked_add, |a, b| a + b)
+}
+
+fn sub(h: &Helper) -> Result<JsonValue, RenderError> {
+    arithmetic(h, i64::checked_sub, |a, b| a - b)
+}
+
+fn mul(h: &Helper) -> Result<JsonValue, RenderError> {
+    arithmetic(h, i64::checked_mul, |a, b| a * b)
+}
+
+/// Integer division when both operands are integers, eg. `{{div 7 2}}`
+/// gives 3.
+fn div(h: &Helper) -> Result<JsonValue, RenderError> {
+    arithmetic(h, i64::checked_div, |a, b| a / b)
+}
+
+fn rem(h: &Helper) -> Result<JsonValue, RenderError> {
+    arithmetic(h, i64::checked_rem, |a, b| a % b)
+}
+
+/// Compares two numbers or two strings.
+fn compare(h: &Helper) -> Result<Ordering, RenderError> {
+    let (a, b) = (param(h, 0)?, param(h, 1)?);
+    let ordering = match (a, b) {
+        (JsonValue::String(a), JsonValue::String(b)) => Some(a.cmp(b)),
+        (JsonValue::Number(_), JsonValue::Number(_)) => {
+            let (a, b) = (number_param(h, 0)?, number_param(h, 1)?);
+            match (a, b) {
+                (Number::Integer(a), Number::Integer(b)) => Some(a.cmp(&b)),
+                (a, b) => a.as_f64().partial_cmp(&b.as_f64()),
+            }
+        }
+        _ => None,
+    };
+    ordering.ok_or_else(|| error(h, "can only compare two numbers or two strings."))
+}
+
+fn gt(h: &Helper) -> Result<JsonValue, RenderError> {
+    Ok((compare(h)? == Ordering::Greater).into())
+}
+
+fn gte(h: &Helper) -> Result<JsonValue, RenderError> {
+    Ok((compare(h)? != Ordering::Less).into())
+}
+
+fn lt(h: &Helper) -> Result<JsonValue, RenderError> {
+    Ok((compare(h)? == Ordering::Less).into())
+}
+
+fn lte(h: &Helper) -> Result<JsonValue, RenderError> {
+    Ok((compare(h)? != Ordering::Greater).into())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        scenario::{Scenario, Template, MAIN_TEMPLATE_NAME},
+        template_engine::{HandlebarsEngine, TemplateEngine},
+    };
+    use anyhow::Result;
+    use serde_json::{json, Value as JsonValue};
+
+    fn render(template: &str, data: JsonValue) -> Result<String> {
+        let scenario = Scenario::new(Template::StringTemplate(template.to_owned()), vec![]);
+        let engine = HandlebarsEngine::with_scenario(&scenario).unwrap();
+        engine.render(MAIN_TEMPLATE_NAME, &data)
+    }
+
+    fn render_each(template: &str, cases: &[(&str, &str)]) {
+        for (input, expected) in cases {
+            assert_eq!(
+                render(template, json!({ "input": input })).unwrap(),
+                *expected,
+                "{} with {:?}",
+                template,
+                input
+            );
+        }
+    }
+
+    #[test]
+    fn case_conversions() {
+        let inputs = ["hello world", "HelloWorld", "hello_world", "HTTPServer-v2"];
+        let expected = [
+            (
+                "snake_case",
+                [
+                    "hello_world",
+                    "hello_world",
+                    "hello_world",
+                    "http_server_v2",
+                ],
+            ),
+            (
+                "camel_case",
+                ["helloWorld", "helloWorld", "helloWorld", "httpServerV2"],
+            ),
+            (
+                "pascal_case",
+                ["HelloWorld", "HelloWorld", "HelloWorld", "HttpServerV2"],
+            ),
+            (
+                "kebab_case",
+                [
+                    "hello-world",
+                    "hello-world",
+                    "hello-world",
+                    "http-server-v2",
+                ],
+            ),
+            (
+                "screaming_snake_case",
+                [
+                    "HELLO_WORLD",
+                    "HELLO_WORLD",
+                    "HELLO_WORLD",
+                    "HTTP_SERVER_V2",
+                ],
+            ),
+        ];
+        for (helper, outputs) in &expected {
+            let cases: Vec<_> = inputs
+                .iter()
+                .copied()
+                .zip(outputs.iter().copied())
+                .collect();
+            render_each(&format!("{{{{{} input}}}}", helper), &cases);
+        }
+        render_each(
+            "{{snake_case input}}",
+            &[("", ""), ("  ", ""), ("a1b", "a1b")],
+        );
+    }
+
+    #[test]
+    fn pluralize() {
+        render_each(
+            "{{pluralize input}}",
+            &[
+                ("item", "items"),
+                ("box", "boxes"),
+                ("match", "matches"),
+                ("entry", "entries"),
+                ("key", "keys"),
+                ("CLASS", "CLASSES"),
+            ],
+        );
+        assert_eq!(
+            render(
+                "{{pluralize \"file\" 1}} {{pluralize \"file\" 0}} {{pluralize \"file\" n}}",
+                json!({"n": 2})
+            )
+            .unwrap(),
+            "file files files"
+        );
+    }
+
+    #[test]
+    fn indent_and_dedent() {
+        let data = json!({"code": "fn main() {\n    run();\n\n}", "indented": "  a\n    b\n\n  c"});
+        assert_eq!(
+            render("{{indent code 2}}", data.clone()).unwrap(),
+            "  fn main() {\n      run();\n\n  }"
+        );
+        assert_eq!(render("{{dedent indented}}", data).unwrap(), "a\n  b\n\nc");
+    }
+
+    #[test]
+    fn join_default_and_replace() {
+        let data = json!({"list": ["a", 1, true], "empty": "", "name": "x"});
+        assert_eq!(
+            render("{{join list \", \"}}", data.clone()).unwrap(),
+            "a, 1, true"
+        );
+        assert_eq!(
+            render(
+                "{{default missing \"d\"}} {{default empty \"d\"}} {{default name \"d\"}}",
+                data.clone()
+            )
+            .unwrap(),
+            "d d x"
+        );
+        assert_eq!(
+            render("{{replace \"a-b-c\" \"-\" \"::\"}}", data).unwrap(),
+            "a::b::c"
+        );
+    }
+
+    #[test]
+    fn padding() {
+        let data = json!({});
+        assert_eq!(
+            render("[{{pad_left \"ab\" 4}}]", data.clone()).unwrap(),
+            "[  ab]"
+        );
+        assert_eq!(
+            render("[{{pad_right \"ab\" 4 \".\"}}]", data.clone()).unwrap(),
+            "[ab..]"
+        );
+        assert_eq!(
+            render("[{{pad_left \"abcdef\" 4}}]", data.clone()).unwrap(),
+            "[abcdef]"
+        );
+        assert!(render("{{pad_left \"ab\" 4 \"--\"}}", data.clone()).is_err());
+        for template in &[
+            "{{pad_left \"ab\" 18446744073709551615}}",
+            "{{pad_right \"ab\" 1001}}",
+            "{{indent \"ab\" 1001}}",
+        ] {
+            let error = render(template, data.clone()).unwrap_err().to_string();
+            assert!(
+                error.contains("parameter 2 must be at most 1000"),
+                "{}",
+                error
+            );
+        }
+    }
+
+    #[test]
+    fn arithmetic() {
+        let data = json!({"a": 7, "b": 2, "f": 0.5});
+        assert_eq!(
+            render(
+                "{{add a b}} {{sub a b}} {{mul a b}} {{div a b}} {{rem a b}} {{add a f}} {{mul (add a 1) b}}",
+                data.clone()
+            )
+            .unwrap(),
+            "9 5 14 3 1 7.5 16"
+        );
+        assert!(render("{{div a 0}}", data.clone()).is_err());
+        assert!(render("{{add a \"b\"}}", data).is_err());
+    }
+
+    #[test]
+    fn comparisons() {
+        let data = json!({"a": 1.5, "b": 2, "s": "abc"});
+        assert_eq!(
+            render(
+                "{{gt b a}} {{gte b 2}} {{lt a b}} {{lte b a}} {{lt s \"abd\"}} \
+                 {{#if (gt (add a b) 3)}}big{{/if}}",
+                data.clone()
+            )
+            .unwrap(),
+            "true true true false true big"
+        );
+        assert!(render("{{gt s 1}}", data).is_err());
+    }
+}
@@ -0,0 +1,253 @@
+//! A [MiniJinja](https://docs.rs/minijinja) engine, for the scenarios
+//! written with Jinja-style templates.
+
+use super::{
+    file_name_key, scripts, template_sources, CompileError, TemplateEngine, TemplateError,
+    FILE_NAME_SUFFIX,
+};
+use crate::scenario::Scenario;
+use anyhow::Result;
+use minijinja::{
+    value::{Kwargs, Rest},
+    Environment, Error, ErrorKind, UndefinedBehavior, Value,
+};
+use serde::Serialize;
+use std::{collections::BTreeMap, sync::Arc};
+
+pub struct JinjaEngine {
+    inner: Environment<'static>,
+}
+
+impl JinjaEngine {
+    pub fn new_uninit() -> Self {
+        Self {
+            inner: Environment::new(),
+        }
+    }
+
+    #[allow(unused)]
+    pub fn with_scenario(scenario: &Scenario) -> Result<Self, Vec<CompileError>> {
+        let mut s = Self::new_uninit();
+        s.set_scenario(scenario)?;
+        Ok(s)
+    }
+
+    /// Replaces the script helpers and the templates with the ones of the
+    /// scenario. If anything fails to compile, the engine is left as is and
+    /// all the errors are returned.
+    pub fn set_scenario(&mut self, scenario: &Scenario) -> Result<(), Vec<CompileError>> {
+        let mut inner = Environment::new();
+        // Generated files end with a newline, like with Handlebars.
+        inner.set_keep_trailing_newline(true);
+        inner.set_undefined_behavior(undefined_behavior(scenario.strict));
+        let escape = scenario.escape;
+        // File names are rendered without escaping, see
+        // `TemplateEngine::render_file_name`.
+        inner.set_formatter(move |out, state, value| {
+            if value.is_safe() || state.name().ends_with(FILE_NAME_SUFFIX) {
+                write!(out, "{}", value)
+            } else {
+                out.write_str(&escape.escape(&value.to_string()))
+            }
+            .map_err(Error::from)
+        });
+
+        let mut errors = register_script_helpers(&mut inner, &scenario.helpers);
+        for (name, source) in template_sources(&scenario.templates) {
+            let source = source.into_owned();
+            if let Err(error) = inner.add_template_owned(name.clone(), source.clone()) {
+                let mut error = template_error(&error, Some(&source));
+                error.template.get_or_insert(name);
+                errors.push(CompileError::Template(error));
+            }
+        }
+
+        if errors.is_empty() {
+            self.inner = inner;
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+
+    /// Overrides the strict mode of the current scenario.
+    pub fn set_strict_mode(&mut self, strict: bool) {
+        self.inner
+            .set_undefined_behavior(undefined_behavior(strict));
+    }
+}
+
+fn undefined_behavior(strict: bool) -> UndefinedBehavior {
+    if strict {
+        UndefinedBehavior::Strict
+    } else {
+        UndefinedBehavior::Lenient
+    }
+}
+
+/// Makes the script helpers available both as functions, eg.
+/// `{{ double(2) }}`, and as filters, eg. `{{ 2 | double }}`. Keyword
+/// arguments are passed in the `hash`.
+fn register_script_helpers(
+    environment: &mut Environment,
+    scripts: &BTreeMap<String, String>,
+) -> Vec<CompileError> {
+    let engine = scripts::script_engine();
+    let mut errors = vec![];
+    for (name, script) in scripts {
+        let helper = match scripts::ScriptHelper::compile(engine.clone(), script) {
+            Ok(helper) => Arc::new(helper),
+            Err(error) => {
+                errors.push(CompileError::ScriptHelper {
+                    name: name.clone(),
+                    error,
+                });
+                continue;
+            }
+        };
+
+        let call = {
+            let name = name.clone();
+            move |params: Rest<Value>, kwargs: Kwargs| -> Result<Value, Error> {
+                let hash = kwargs
+                    .args()
+                    .map(|key| Ok((key, kwargs.get::<Value>(key)?)))
+                    .collect::<Result<BTreeMap<&str, Value>, Error>>()?;
+                helper
+                    .eval(&params.0, hash)
+                    .map(Value::from_serialize)
+                    .map_err(|e| {
+                        Error::new(
+                            ErrorKind::InvalidOperation,
+                            format!("`{}` helper: {}", name, e),
+                        )
+                    })
+            }
+        };
+        environment.add_function(name.clone(), call.clone());
+        environment.add_filter(name.clone(), call);
+    }
+    errors
+}
+
+/// Locates a MiniJinja error, which only knows the byte range of the error
+/// in the template source.
+fn template_error(error: &Error, source: Option<&str>) -> TemplateError {
+    let reason = match error.detail() {
+        Some(detail) => format!("{}: {}", error.kind(), detail),
+        None => error.kind().to_string(),
+    };
+    let column = error
+        .range()
+        .zip(source)
+        .and_then(|(range, source)| source.get(..range.start))
+        .map(|before| before.rsplit('\n').next().unwrap_or("").chars().count() + 1);
+    TemplateError {
+        template: error.name().map(ToOwned::to_owned),
+        position: error.line().zip(column),
+        reason: super::error::capitalize(&reason),
+    }
+}
+
+impl TemplateEngine for JinjaEngine {
+    fn render<T: Serialize>(&self, template_name: &str, data: &T) -> Result<String> {
+        let template = self.inner.get_template(template_name)?;
+        template.render(data).map_err(|error| {
+            // The error may come from an included template.
+            let source = error
+                .name()
+                .and_then(|name| self.inner.get_template(name).ok())
+                .map(|template| template.source().to_owned());
+            template_error(&error, source.as_deref()).into()
+        })
+    }
+
+    fn render_file_name<T: Serialize>(&self, template_name: &str, data: &T) -> Result<String> {
+        self.render(&file_name_key(template_name), data)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn engine(definition: serde_json::Value) -> Result<JinjaEngine, Vec<CompileError>> {
+        JinjaEngine::with_scenario(&serde_json::from_value(definition).unwrap())
+    }
+
+    #[test]
+    fn render_with_includes_and_escaping() {
+        let engine = engine(json!({
+            "engine": "jinja",
+            "templates": [
+                {"name": "header", "template": "# {{ title }}\n"},
+                {
+                    "name": "main",
+                    "template": "{% include \"header\" %}{% for item in items %}- {{ item.name | upper }}\n{% endfor %}",
+                    "output": true
+                }
+            ],
+            "escape": "sql",
+            "inputs": []
+        }))
+        .ok()
+        .unwrap();
+        let data = json!({"title": "List", "items": [{"name": "a'b"}, {"name": "c"}]});
+        assert_eq!(
+            engine.render("main", &data).unwrap(),
+            "# 'List'\n- 'A''B'\n- 'C'\n"
+        );
+    }
+
+    #[test]
+    fn script_helpers_as_functions_and_filters() {
+        let engine = engine(json!({
+            "engine": "jinja",
+            "template": "{{ greet(name, punctuation=\"!\") }} {{ name | greet }}",
+            "helpers": {"greet": "\"Hello \" + params[0] + if \"punctuation\" in hash { hash.punctuation } else { \"\" }"},
+            "escape": "none",
+            "inputs": []
+        }))
+        .ok()
+        .unwrap();
+        assert_eq!(
+            engine.render("main", &json!({"name": "you"})).unwrap(),
+            "Hello you! Hello you"
+        );
+    }
+
+    #[test]
+    fn compile_and_render_errors_are_located() {
+        let errors = engine(json!({
+            "engine": "jinja",
+            "template": "ok\n  {% for x in %}{% endfor %}",
+            "inputs": []
+        }))
+        .err()
+        .unwrap();
+        match &errors[..] {
+            [CompileError::Template(error)] => {
+                assert_eq!(error.template.as_deref(), Some("main"));
+                assert_eq!(error.position.map(|(line, _)| line), Some(2));
+            }
+            _ => panic!("Unexpected errors: {:?}", errors),
+        }
+
+        let mut engine = engine(json!({
+            "engine": "jinja",
+            "template": "{{ a }}\n  {{ b }}",
+            "strict": true,
+            "inputs": []
+        }))
+        .ok()
+        .unwrap();
+        let error = engine.render("main", &json!({"a": 1})).unwrap_err();
+        let error = error.downcast_ref::<TemplateError>().unwrap();
+        assert_eq!(error.position, Some((2, 6)));
+        assert!(error.reason.starts_with("Undefined value"));
+
+        engine.set_strict_mode(false);
+        assert_eq!(engine.render("main", &json!({"a": 1})).unwrap(), "1\n  ");
+    }
+}
@@ -0,0 +1,538 @@
+use crate::{
+    highlight::Language,
+    output_format::{Format, FormatError},
+    scenario::{NamedTemplate, Scenario},
+};
+use anyhow::Result;
+use core::fmt;
+use handlebars::Handlebars;
+use serde::{Deserialize, Serialize};
+use std::{borrow::Cow, collections::BTreeMap, iter};
+
+mod error;
+mod escape;
+mod helpers;
+mod jinja;
+mod scripts;
+mod trace;
+
+pub(crate) use error::capitalize;
+pub use error::{excerpt, TemplateError};
+pub use escape::Escape;
+pub use jinja::JinjaEngine;
+pub use trace::{LineSources, SourceMap, Tracer};
+
+/// The template engine used by a scenario.
+///
+/// The code generation helpers and the `escape` helper are only available
+/// to Handlebars templates, Jinja templates have the builtin filters of
+/// MiniJinja instead. Script helpers are available to both.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum Engine {
+    #[default]
+    Handlebars,
+    #[serde(alias = "minijinja")]
+    Jinja,
+}
+
+impl Engine {
+    pub fn is_default(&self) -> bool {
+        *self == Engine::default()
+    }
+}
+
+pub trait TemplateEngine {
+    fn render<T: Serialize>(&self, template_name: &str, data: &T) -> Result<String>;
+
+    /// Renders the file name of an output, registered with
+    /// [`file_name_key`]. File names are never escaped, since the escaping
+    /// of the scenario is meant for the content of its outputs.
+    fn render_file_name<T: Serialize>(&self, template_name: &str, data: &T) -> Result<String>;
+
+    /// Renders the output templates of a scenario and their file names,
+    /// which must have been registered with [`file_name_key`].
+    fn render_outputs<T: Serialize>(&self, scenario: &Scenario, data: &T) -> Vec<RenderedOutput> {
+        scenario
+            .outputs()
+            .map(|template| {
+                let file_name = match &template.file_name {
+                    Some(_) => self.render_file_name(&template.name, data),
+                    None => Ok(template.name.clone()),
+                };
+                match file_name {
+                    Ok(file_name) => {
+                        let mut output = RenderedOutput {
+                            language: template.language(&file_name),
+                            file_name,
+                            format: template.format,
+                            content: self.render(&template.name, data),
+                            format_error: None,
+                        };
+                        output.check_format(template.pretty);
+                        output
+                    }
+                    Err(e) => RenderedOutput {
+                        file_name: template.name.clone(),
+                        language: template.language(&template.name),
+                        format: template.format,
+                        content: Err(e.context("Failed to render the file name")),
+                        format_error: None,
+                    },
+                }
+            })
+            .collect()
+    }
+}
+
+/// An output template rendered as a file.
+#[derive(Debug)]
+pub struct RenderedOutput {
+    pub file_name: String,
+    pub language: Option<Language>,
+    pub format: Option<Format>,
+    pub content: Result<String>,
+    /// Set when the content doesn't parse in its declared format.
+    pub format_error: Option<FormatError>,
+}
+
+impl RenderedOutput {
+    /// Parses the content in its declared format, if any, and replaces it
+    /// with the parsed value printed back if `pretty` is set.
+    fn check_format(&mut self, pretty: bool) {
+        if let (Some(format), Ok(content)) = (self.format, &mut self.content) {
+            let checked = if pretty {
+                format
+                    .pretty_print(content)
+                    .map(|printed| *content = printed)
+            } else {
+                format.validate(content)
+            };
+            self.format_error = checked.err();
+        }
+    }
+}
+
+/// Suffix of the names under which the file name templates are registered.
+const FILE_NAME_SUFFIX: &str = "#file_name";
+
+/// Name under which the file name template of an output is registered.
+pub fn file_name_key(template_name: &str) -> String {
+    format!("{}{}", template_name, FILE_NAME_SUFFIX)
+}
+
+/// The sources of the templates of a scenario and of their file names, by
+/// registration name.
+fn template_sources(templates: &[NamedTemplate]) -> impl Iterator<Item = (String, Cow<'_, str>)> {
+    templates.iter().flat_map(|template| {
+        let file_name = template.file_name.as_ref().map(|file_name| {
+            (
+                file_name_key(&template.name),
+                Cow::Borrowed(file_name.as_str()),
+            )
+        });
+        iter::once((template.name.clone(), template.template.source())).chain(file_name)
+    })
+}
+
+/// Finds the source of a template registered from the scenario, including
+/// the templates of file names.
+pub fn template_source<'a>(scenario: &'a Scenario, name: &str) -> Option<Cow<'a, str>> {
+    scenario.templates.iter().find_map(|template| {
+        if template.name == name {
+            Some(template.template.source())
+        } else if file_name_key(&template.name) == name {
+            template.file_name.as_deref().map(Cow::Borrowed)
+        } else {
+            None
+        }
+    })
+}
+
+/// The engine declared by the loaded scenario.
+pub enum ScenarioEngine {
+    Handlebars(HandlebarsEngine),
+    Jinja(JinjaEngine),
+}
+
+impl ScenarioEngine {
+    pub fn new_uninit() -> Self {
+        ScenarioEngine::Handlebars(HandlebarsEngine::new_uninit())
+    }
+
+    #[allow(unused)]
+    pub fn with_scenario(scenario: &Scenario) -> Result<Self, Vec<CompileError>> {
+        let mut s = Self::new_uninit();
+        s.set_scenario(scenario)?;
+        Ok(s)
+    }
+
+    /// Switches to the engine of the scenario, see
+    /// [`HandlebarsEngine::set_scenario`].
+    pub fn set_scenario(&mut self, scenario: &Scenario) -> Result<(), Vec<CompileError>> {
+        *self = match scenario.engine {
+            Engine::Handlebars => {
+                ScenarioEngine::Handlebars(HandlebarsEngine::with_scenario(scenario)?)
+            }
+            Engine::Jinja => ScenarioEngine::Jinja(JinjaEngine::with_scenario(scenario)?),
+        };
+        Ok(())
+    }
+
+    /// Overrides the strict mode of the current scenario.
+    pub fn set_strict_mode(&mut self, strict: bool) {
+        match self {
+            ScenarioEngine::Handlebars(engine) => engine.set_strict_mode(strict),
+            ScenarioEngine::Jinja(engine) => engine.set_strict_mode(strict),
+        }
+    }
+}
+
+impl TemplateEngine for ScenarioEngine {
+    fn render<T: Serialize>(&self, template_name: &str, data: &T) -> Result<String> {
+        match self {
+            ScenarioEngine::Handlebars(engine) => engine.render(template_name, data),
+            ScenarioEngine::Jinja(engine) => engine.render(template_name, data),
+        }
+    }
+
+    fn render_file_name<T: Serialize>(&self, template_name: &str, data: &T) -> Result<String> {
+        match self {
+            ScenarioEngine::Handlebars(engine) => engine.render_file_name(template_name, data),
+            ScenarioEngine::Jinja(engine) => engine.render_file_name(template_name, data),
+        }
+    }
+}
+
+pub struct HandlebarsEngine {
+    inner: Handlebars<'static>,
+    /// The same templates, without escaping, to render the file names.
+    file_names: Handlebars<'static>,
+}
+
+/// A template or a script helper of a scenario which failed to compile.
+#[derive(Debug)]
+pub enum CompileError {
+    /// The error is located in the template it names.
+    Template(TemplateError),
+    ScriptHelper {
+        name: String,
+        error: rhai::ParseError,
+    },
+}
+
+impl fmt::Display for CompileError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CompileError::Template(error) => write!(f, "Failed to compile: {}", error),
+            CompileError::ScriptHelper { name, error } => {
+                write!(f, "Failed to compile the helper '{}': {}", name, error)
+            }
+        }
+    }
+}
+
+impl HandlebarsEngine {
+    pub fn new_uninit() -> Self {
+        Self {
+            inner: new_registry(),
+            file_names: new_registry(),
+        }
+    }
+
+    #[allow(unused)]
+    pub fn with_scenario(scenario: &Scenario) -> Result<Self, Vec<CompileError>> {
+        let mut s = Self::new_uninit();
+        s.set_scenario(scenario)?;
+        Ok(s)
+    }
+
+    /// Replaces the script helpers and the templates with the ones of the
+    /// scenario. If anything fails to compile, the engine is left as is and
+    /// all the errors are returned.
+    pub fn set_scenario(&mut self, scenario: &Scenario) -> Result<(), Vec<CompileError>> {
+        let mut inner = new_registry();
+        inner.set_strict_mode(scenario.strict);
+        let escape = scenario.escape;
+        inner.register_escape_fn(move |value| escape.escape(value));
+        let mut errors = register_script_helpers(&mut inner, &scenario.helpers);
+        errors.extend(register_templates(&mut inner, &scenario.templates));
+        if !errors.is_empty() {
+            return Err(errors);
+        }
+
+        // The templates compile, so they are registered again without errors.
+        let mut file_names = new_registry();
+        file_names.set_strict_mode(scenario.strict);
+        file_names.register_escape_fn(handlebars::no_escape);
+        register_script_helpers(&mut file_names, &scenario.helpers);
+        register_templates(&mut file_names, &scenario.templates);
+
+        self.inner = inner;
+        self.file_names = file_names;
+        Ok(())
+    }
+
+    /// Overrides the strict mode of the current scenario.
+    pub fn set_strict_mode(&mut self, strict: bool) {
+        self.inner.set_strict_mode(strict);
+        self.file_names.set_strict_mode(strict);
+    }
+}
+
+/// Creates a registry with the built-in helpers.
+fn new_registry() -> Handlebars<'static> {
+    let mut handlebars = Handlebars::default();
+    helpers::register_helpers(&mut handlebars);
+    handlebars.register_helper("escape", Box::new(escape::EscapeHelper));
+    handlebars
+}
+
+/// Registers the script helpers, which replace any built-in helper with
+/// the same name.
+fn register_script_helpers(
+    handlebars: &mut Handlebars,
+    scripts: &BTreeMap<String, String>,
+) -> Vec<CompileError> {
+    let engine = scripts::script_engine();
+    scripts
+        .iter()
+        .filter_map(
+            |(name, script)| match scripts::ScriptHelper::compile(engine.clone(), script) {
+                Ok(helper) => {
+                    handlebars.register_helper(name, Box::new(helper));
+                    None
+                }
+                Err(error) => Some(CompileError::ScriptHelper {
+                    name: name.clone(),
+                    error,
+                }),
+            },
+        )
+        .collect()
+}
+
+/// Registers the templates, each one being also available to the others
+/// as a partial, and the templates of their file names.
+fn register_templates(
+    handlebars: &mut Handlebars,
+    templates: &[NamedTemplate],
+) -> Vec<CompileError> {
+    template_sources(templates)
+        .filter_map(|(name, source)| {
+            handlebars
+                .register_template_string(&name, source)
+                .err()
+                .map(|error| CompileError::Template(error.into()))
+        })
+        .collect()
+}
+
+impl TemplateEngine for HandlebarsEngine {
+    fn render<T: Serialize>(&self, template_name: &str, data: &T) -> Result<String> {
+        self.inner
+            .render(template_name, &data)
+            .map_err(|e| TemplateError::from(e).into())
+    }
+
+    fn render_file_name<T: Serialize>(&self, template_name: &str, data: &T) -> Result<String> {
+        self.file_names
+            .render(&file_name_key(template_name), &data)
+            .map_err(|e| TemplateError::from(e).into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn scenario(definition: serde_json::Value) -> Scenario {
+        serde_json::from_value(definition).unwrap()
+    }
+
+    #[test]
+    fn render_with_partials() {
+        let engine = HandlebarsEngine::with_scenario(&scenario(json!({
+            "templates": [
+                {"name": "header", "template": "# {{title}}\n"},
+                {
+                    "name": "main",
+                    "template": "{{> header}}{{#each items}}- {{> item}}\n{{/each}}",
+                    "output": true
+                },
+                {"name": "item", "template": "{{name}}"}
+            ],
+            "inputs": []
+        })))
+        .unwrap();
+        let data = json!({"title": "List", "items": [{"name": "a"}, {"name": "b"}]});
+        assert_eq!(engine.render("main", &data).unwrap(), "# List\n- a\n- b\n");
+        assert_eq!(engine.render("item", &json!({"name": "c"})).unwrap(), "c");
+    }
+
+    #[test]
+    fn each_compile_error_is_reported() {
+        let errors = HandlebarsEngine::with_scenario(&scenario(json!({
+            "templates": [
+                {"name": "a", "template": "{{#if}}", "output": true},
+                {"name": "b", "template": "ok"},
+                {"name": "c", "template": "{{/each}}"}
+            ],
+            "helpers": {"ok": "1", "broken": "let x = ;"},
+            "inputs": []
+        })))
+        .err()
+        .unwrap();
+        let names: Vec<&str> = errors
+            .iter()
+            .map(|error| match error {
+                CompileError::Template(error) => error.template.as_deref().unwrap(),
+                CompileError::ScriptHelper { name, .. } => name.as_str(),
+            })
+            .collect();
+        assert_eq!(names, vec!["broken", "a", "c"]);
+    }
+
+    #[test]
+    fn failed_compilation_keeps_the_previous_scenario() {
+        let mut engine = HandlebarsEngine::with_scenario(&scenario(json!({
+            "template": "{{double 2}}",
+            "helpers": {"double": "params[0] * 2"},
+            "inputs": []
+        })))
+        .unwrap();
+        assert!(engine
+            .set_scenario(&scenario(json!({"template": "{{#if}}", "inputs": []})))
+            .is_err());
+        assert_eq!(engine.render("main", &json!({})).unwrap(), "4");
+    }
+
+    #[test]
+    fn strict_mode() {
+        let template = "{{#each items}}\n  {{name}} {{value}}\n{{/each}}";
+        let data = json!({"items": [{"name": "a", "value": 1}, {"name": "b"}]});
+        let mut engine = HandlebarsEngine::with_scenario(&scenario(json!({
+            "template": template,
+            "strict": true,
+            "inputs": []
+        })))
+        .unwrap();
+        assert_eq!(
+            engine.render("main", &data).unwrap_err().to_string(),
+            "Missing variable 'value' in template 'main' at line 2, column 12."
+        );
+
+        engine.set_strict_mode(false);
+        assert_eq!(engine.render("main", &data).unwrap(), "\n  a 1\n\n  b \n");
+    }
+
+    #[test]
+    fn scenario_engine() {
+        let mut engine = ScenarioEngine::with_scenario(&scenario(json!({
+            "template": "{{#if a}}{{a}}{{/if}}",
+            "inputs": []
+        })))
+        .ok()
+        .unwrap();
+        assert_eq!(engine.render("main", &json!({"a": 1})).unwrap(), "1");
+
+        let jinja = scenario(json!({
+            "engine": "jinja",
+            "template": "{% if a %}{{ a }}{% endif %}",
+            "inputs": []
+        }));
+        engine.set_scenario(&jinja).unwrap();
+        assert_eq!(engine.render("main", &json!({"a": 1})).unwrap(), "1");
+    }
+
+    #[test]
+    fn render_error_of_a_file_name() {
+        let scenario = scenario(json!({
+            "templates": [
+                {"name": "a", "template": "", "output": true, "file_name": "{{add x}}.txt"}
+            ],
+            "inputs": []
+        }));
+        let engine = HandlebarsEngine::with_scenario(&scenario).unwrap();
+        let outputs = engine.render_outputs(&scenario, &json!({"x": 1}));
+        let error = outputs[0].content.as_ref().unwrap_err();
+        let error = error.downcast_ref::<TemplateError>().unwrap();
+        assert_eq!(error.template.as_deref(), Some("a#file_name"));
+        assert_eq!(error.position, Some((1, 1)));
+        let source = template_source(&scenario, "a#file_name").unwrap();
+        assert_eq!(error.excerpt(&source).unwrap(), "1 | {{add x}}.txt\n    ^");
+    }
+
+    #[test]
+    fn render_outputs_with_file_names() {
+        let scenario: Scenario = serde_json::from_value(json!({
+            "templates": [
+                {"name": "Cargo.toml", "template": "name = \"{{name}}\"", "output": true},
+                {
+                    "name": "lib",
+                    "template": "pub mod {{module}};",
+                    "output": true,
+                    "file_name": "src/{{module}}.rs"
+                },
+                {"name": "partial", "template": ""}
+            ],
+            "inputs": []
+        }))
+        .unwrap();
+        let engine = HandlebarsEngine::with_scenario(&scenario).unwrap();
+        let outputs: Vec<(String, String)> = engine
+            .render_outputs(&scenario, &json!({"name": "demo", "module": "app"}))
+            .into_iter()
+            .map(|output| (output.file_name, output.content.unwrap()))
+            .collect();
+        assert_eq!(
+            outputs,
+            vec![
+                ("Cargo.toml".to_owned(), "name = \"demo\"".to_owned()),
+                ("src/app.rs".to_owned(), "pub mod app;".to_owned()),
+            ]
+        );
+    }
+
+    #[test]
+    fn file_names_are_not_escaped() {
+        let cases = [("shell", "'A&B'"), ("html", "A&amp;B")];
+        for engine in &["handlebars", "jinja"] {
+            for (escape, content) in &cases {
+                let scenario = scenario(json!({
+                    "engine": engine,
+                    "escape": escape,
+                    "templates": [
+                        {"name": "lib", "template": "{{m}}", "output": true, "file_name": "src/{{m}}.rs"}
+                    ],
+                    "inputs": []
+                }));
+                let engine = ScenarioEngine::with_scenario(&scenario).unwrap();
+                let outputs = engine.render_outputs(&scenario, &json!({"m": "A&B"}));
+                assert_eq!(outputs[0].file_name, "src/A&B.rs", "{}", escape);
+                assert_eq!(outputs[0].content.as_ref().unwrap(), content);
+            }
+        }
+    }
+
+    #[test]
+    fn render_outputs_with_formats() {
+        let scenario = scenario(json!({
+            "templates": [
+                {"name": "a.json", "template": "[{{a}},]", "output": true, "format": "json"},
+                {"name": "b", "template": "{b: {{a}}}", "output": true, "format": "yaml", "pretty": true}
+            ],
+            "inputs": []
+        }));
+        let engine = HandlebarsEngine::with_scenario(&scenario).unwrap();
+        let outputs = engine.render_outputs(&scenario, &json!({"a": 1}));
+
+        let error = outputs[0].format_error.as_ref().unwrap();
+        assert_eq!(error.position, Some((1, 4)));
+        assert_eq!(outputs[0].content.as_ref().unwrap(), "[1,]");
+        assert_eq!(outputs[1].format_error, None);
+        assert_eq!(outputs[1].content.as_ref().unwrap(), "b: 1\n");
+        assert_eq!(outputs[1].language, Some(Language::Yaml));
+    }
+}
@@ -0,0 +1,155 @@
+//! Helpers defined by scenarios as [Rhai](https://rhai.rs) scripts.
+
+use handlebars::{Context, Handlebars, Helper, HelperDef, RenderContext, RenderError, ScopedJson};
+use rhai::{
+    de::from_dynamic, ser::to_dynamic, Dynamic, Engine, EvalAltResult, ParseError, Scope, AST,
+};
+use serde::Serialize;
+use serde_json::Value as JsonValue;
+use std::{collections::HashMap, sync::Arc};
+
+/// Maximum number of operations of one helper call, so that an infinite
+/// loop fails instead of freezing the page.
+const MAX_OPERATIONS: u64 = 100_000;
+const MAX_CALL_LEVELS: usize = 32;
+const MAX_EXPRESSION_DEPTH: usize = 64;
+const MAX_STRING_SIZE: usize = 1 << 20;
+const MAX_COLLECTION_SIZE: usize = 10_000;
+
+/// Creates a Rhai engine with execution limits, and without modules.
+pub fn script_engine() -> Arc<Engine> {
+    let mut engine = Engine::new();
+    engine
+        .set_max_operations(MAX_OPERATIONS)
+        .set_max_call_levels(MAX_CALL_LEVELS)
+        .set_max_expr_depths(MAX_EXPRESSION_DEPTH, MAX_EXPRESSION_DEPTH)
+        .set_max_string_size(MAX_STRING_SIZE)
+        .set_max_array_size(MAX_COLLECTION_SIZE)
+        .set_max_map_size(MAX_COLLECTION_SIZE)
+        .set_max_modules(0);
+    Arc::new(engine)
+}
+
+pub struct ScriptHelper {
+    engine: Arc<Engine>,
+    script: AST,
+}
+
+impl ScriptHelper {
+    pub fn compile(engine: Arc<Engine>, script: &str) -> Result<Self, ParseError> {
+        let script = engine.compile(script)?;
+        Ok(Self { engine, script })
+    }
+
+    /// Runs the script with the given parameters and hash, independently
+    /// of the template engine calling it.
+    pub fn eval<P: Serialize, H: Serialize>(
+        &self,
+        params: P,
+        hash: H,
+    ) -> Result<JsonValue, Box<EvalAltResult>> {
+        let mut scope = Scope::new();
+        scope.push_dynamic("params", to_dynamic(params)?);
+        scope.push_dynamic("hash", to_dynamic(hash)?);
+
+        let result = self
+            .engine
+            .eval_ast_with_scope::<Dynamic>(&mut scope, &self.script)?;
+        from_dynamic(&result)
+    }
+
+    fn call(&self, h: &Helper) -> Result<JsonValue, RenderError> {
+        let params: Vec<&JsonValue> = h.params().iter().map(|param| param.value()).collect();
+        let hash: HashMap<&str, &JsonValue> = h
+            .hash()
+            .iter()
+            .map(|(key, param)| (*key, param.value()))
+            .collect();
+        self.eval(params, hash)
+            .map_err(|e| RenderError::new(format!("`{}` helper: {}", h.name(), e)))
+    }
+}
+
+impl HelperDef for ScriptHelper {
+    fn call_inner<'reg: 'rc, 'rc>(
+        &self,
+        h: &Helper<'reg, 'rc>,
+        _: &'reg Handlebars,
+        _: &'rc Context,
+        _: &mut RenderContext<'reg, 'rc>,
+    ) -> Result<Option<ScopedJson<'reg, 'rc>>, RenderError> {
+        self.call(h).map(|value| Some(ScopedJson::Derived(value)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        scenario::{Scenario, MAIN_TEMPLATE_NAME},
+        template_engine::{HandlebarsEngine, TemplateEngine},
+    };
+    use anyhow::Result;
+    use serde_json::json;
+
+    fn render(template: &str, script: &str) -> Result<String> {
+        let scenario: Scenario = serde_json::from_value(json!({
+            "template": template,
+            "helpers": {"script": script},
+            "inputs": []
+        }))
+        .unwrap();
+        let engine = HandlebarsEngine::with_scenario(&scenario).unwrap();
+        engine.render(
+            MAIN_TEMPLATE_NAME,
+            &json!({"name": "world", "list": [1, 2, 3]}),
+        )
+    }
+
+    #[test]
+    fn params_and_hash() {
+        assert_eq!(
+            render(
+                "{{script name list sep=\", \"}}",
+                "let total = 0; for x in params[1] { total += x; } \
+                 \"Hello \" + params[0] + hash.sep + total"
+            )
+            .unwrap(),
+            "Hello world, 6"
+        );
+    }
+
+    #[test]
+    fn structured_results_in_subexpressions() {
+        assert_eq!(
+            render(
+                "{{#each (script name)}}{{this.a}}{{/each}}",
+                "[#{a: 1}, #{a: \"b\"}]"
+            )
+            .unwrap(),
+            "1b"
+        );
+    }
+
+    #[test]
+    fn execution_is_limited() {
+        let error = render("{{script}}", "loop {}").unwrap_err();
+        assert!(format!("{:#}", error).contains("`script` helper"));
+        assert!(render("{{script}}", "let s = \"a\"; loop { s += s; }").is_err());
+        assert!(render("{{script}}", "fn f(x) { f(x) } f(1)").is_err());
+    }
+
+    #[test]
+    fn script_helpers_replace_builtin_ones() {
+        let scenario: Scenario = serde_json::from_value(json!({
+            "template": "{{snake_case \"A\"}}",
+            "helpers": {"snake_case": "\"custom\""},
+            "inputs": []
+        }))
+        .unwrap();
+        let engine = HandlebarsEngine::with_scenario(&scenario).unwrap();
+        assert_eq!(
+            engine.render(MAIN_TEMPLATE_NAME, &json!({})).unwrap(),
+            "custom"
+        );
+    }
+}
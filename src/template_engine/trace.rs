@@ -0,0 +1,547 @@
+//! Tracing renders, mapping the fragments of an output back to the elements
+//! of the templates which produced them and to the data they read.
+//!
+//! The compiled templates are instrumented with calls to a hidden helper
+//! before each of their elements, recursively. The helper records the
+//! paths its parameters were resolved to, and writes a marker in the
+//! output, which is then stripped to find where each element wrote.
+
+use super::{HandlebarsEngine, TemplateEngine};
+use crate::{json_path::Path, prelude::*, scenario::Scenario};
+use handlebars::{
+    template::{HelperTemplate, Parameter, Subexpression, Template, TemplateElement},
+    Context, Handlebars, Helper, HelperDef, HelperResult, Output, RenderContext, RenderError,
+};
+use std::{
+    collections::HashMap,
+    iter, mem,
+    ops::Range,
+    sync::{Arc, Mutex},
+};
+
+const TRACE_HELPER: &str = "__trace";
+const TRACE_END_HELPER: &str = "__trace_end";
+
+// Unicode noncharacters, which aren't expected in the outputs.
+const START_MARKER: char = '\u{FDD0}';
+const END_MARKER: char = '\u{FDD1}';
+const CLOSE_MARKER: char = '\u{FDD2}';
+
+/// An element of a template, eg. a text or an expression.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct TracedElement {
+    pub template: String,
+    /// Line and column, both starting at 1.
+    pub position: Option<(usize, usize)>,
+    /// Whether the element is raw text from the template.
+    pub text: bool,
+    /// Whether the element contains other elements, eg. `{{#each}}`.
+    pub block: bool,
+}
+
+/// What an element wrote in one place of the output. Elements in loops
+/// write several spans.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct TracedSpan {
+    /// Byte range in the output. Spans of blocks contain the spans of
+    /// their elements.
+    pub start: usize,
+    pub end: usize,
+    /// Index of the element in [`SourceMap::elements`].
+    pub element: usize,
+    /// The paths of the data read by the parameters of the element.
+    pub reads: Vec<Path>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
+pub struct SourceMap {
+    pub elements: Vec<TracedElement>,
+    /// Sorted by start.
+    pub spans: Vec<TracedSpan>,
+}
+
+/// A line of a template which produced a line of an output.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TemplateLine {
+    pub template: String,
+    pub line: usize,
+}
+
+/// Where a line of an output comes from.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct LineSources {
+    pub template_lines: Vec<TemplateLine>,
+    pub reads: Vec<Path>,
+}
+
+impl SourceMap {
+    /// Finds the template lines which wrote the line of `content` at
+    /// `line`, starting at 0, and the data read by them and by the blocks
+    /// containing them.
+    pub fn line_sources(&self, content: &str, line: usize) -> LineSources {
+        let line_start = content
+            .split('\n')
+            .take(line)
+            .map(|line| line.len() + 1)
+            .sum::<usize>();
+        let line_end = content[line_start.min(content.len())..]
+            .find('\n')
+            .map_or(content.len(), |end| line_start + end);
+
+        let mut sources = LineSources::default();
+        for span in &self.spans {
+            // Empty spans are kept to show what was read to write nothing.
+            let on_line = if span.start == span.end {
+                line_start <= span.start && span.start <= line_end
+            } else {
+                span.start < line_end.max(line_start + 1) && line_start < span.end
+            };
+            if !on_line {
+                continue;
+            }
+            let element = &self.elements[span.element];
+            if let (false, Some((line, _))) = (element.block, element.position) {
+                // Texts may span several lines of their template, and are
+                // positioned after their leading whitespace.
+                let line = if element.text {
+                    let text = &content[span.start..span.end];
+                    let leading = &text[..text.len() - text.trim_start().len()];
+                    let before_line = &content[span.start..line_start.max(span.start)];
+                    (line + before_line.matches('\n').count())
+                        .saturating_sub(leading.matches('\n').count())
+                } else {
+                    line
+                };
+                let template_line = TemplateLine {
+                    template: element.template.clone(),
+                    line,
+                };
+                if !sources.template_lines.contains(&template_line) {
+                    sources.template_lines.push(template_line);
+                }
+            }
+            for read in &span.reads {
+                if !sources.reads.contains(read) {
+                    sources.reads.push(read.clone());
+                }
+            }
+        }
+        sources
+    }
+
+    /// Returns the sorted and merged byte ranges of the output written by
+    /// the elements reading `path`, its parents or its children. Blocks
+    /// reading its parents, eg. looping over its list, are left out.
+    pub fn fragments_reading(&self, path: &Path) -> Vec<Range<usize>> {
+        let mut fragments: Vec<Range<usize>> = vec![];
+        for span in &self.spans {
+            let block = self.elements[span.element].block;
+            let affected = span
+                .reads
+                .iter()
+                .any(|read| is_prefix(path, read) || (!block && is_prefix(read, path)));
+            if !affected || span.start == span.end {
+                continue;
+            }
+            match fragments.last_mut() {
+                Some(last) if span.start <= last.end => last.end = last.end.max(span.end),
+                _ => fragments.push(span.start..span.end),
+            }
+        }
+        fragments
+    }
+}
+
+fn is_prefix(prefix: &Path, path: &Path) -> bool {
+    let mut path = path.get_segments();
+    prefix
+        .get_segments()
+        .all(|segment| path.next() == Some(segment))
+}
+
+/// Renders the outputs of a scenario along with their source maps.
+pub struct Tracer {
+    engine: HandlebarsEngine,
+    elements: Vec<TracedElement>,
+    events: Arc<Mutex<Vec<TraceEvent>>>,
+}
+
+struct TraceEvent {
+    element: usize,
+    reads: Vec<Path>,
+}
+
+impl Tracer {
+    /// Compiles the scenario with its templates instrumented. The tracer
+    /// isn't strict, so that the missing variables don't stop the render.
+    pub fn new(scenario: &Scenario) -> Result<Self, Vec<super::CompileError>> {
+        let mut engine = HandlebarsEngine::with_scenario(scenario)?;
+        engine.set_strict_mode(false);
+
+        let mut elements = vec![];
+        let mut templates: Vec<(String, Template)> = engine
+            .inner
+            .get_templates()
+            .iter()
+            .map(|(name, template)| (name.clone(), template.clone()))
+            .collect();
+        // The ids of the elements don't depend on the order of the map.
+        templates.sort_by(|(a, _), (b, _)| a.cmp(b));
+        for (name, mut template) in templates {
+            instrument(&name, &mut template, &mut elements);
+            engine.inner.register_template(&name, template);
+        }
+
+        let events = Arc::new(Mutex::new(vec![]));
+        engine.inner.register_helper(
+            TRACE_HELPER,
+            Box::new(TraceHelper {
+                events: events.clone(),
+            }),
+        );
+        engine
+            .inner
+            .register_helper(TRACE_END_HELPER, Box::new(TraceEndHelper));
+
+        Ok(Self {
+            engine,
+            elements,
+            events,
+        })
+    }
+
+    pub fn render<T: Serialize>(
+        &self,
+        template_name: &str,
+        data: &T,
+    ) -> Result<(String, SourceMap)> {
+        self.events.lock().unwrap().clear();
+        let output = self.engine.render(template_name, data)?;
+        let events = mem::take(&mut *self.events.lock().unwrap());
+        Ok(strip_markers(&output, events, &self.elements))
+    }
+}
+
+/// Inserts the calls to the tracing helpers in the template and in the
+/// templates of its blocks.
+fn instrument(name: &str, template: &mut Template, elements: &mut Vec<TracedElement>) {
+    let mapping = template.mapping.take().unwrap_or_default();
+    let mut instrumented = vec![];
+    let mut instrumented_mapping = vec![];
+
+    for (index, mut element) in mem::take(&mut template.elements).into_iter().enumerate() {
+        let position = mapping.get(index).cloned();
+        let mut push = |element| {
+            instrumented.push(element);
+            instrumented_mapping.extend(position.clone());
+        };
+        if let TemplateElement::Comment(_) = element {
+            push(element);
+            continue;
+        }
+
+        let nested: Vec<&mut Template> = match &mut element {
+            TemplateElement::HelperBlock(helper) => helper
+                .template
+                .iter_mut()
+                .chain(&mut helper.inverse)
+                .collect(),
+            TemplateElement::DecoratorBlock(decorator)
+            | TemplateElement::PartialBlock(decorator) => decorator.template.iter_mut().collect(),
+            _ => vec![],
+        };
+        for template in nested {
+            instrument(name, template, elements);
+        }
+
+        let block = matches!(
+            element,
+            TemplateElement::HelperBlock(_)
+                | TemplateElement::DecoratorBlock(_)
+                | TemplateElement::PartialBlock(_)
+                | TemplateElement::PartialExpression(_)
+        );
+        let id = elements.len();
+        elements.push(TracedElement {
+            template: name.to_owned(),
+            position: position.as_ref().map(|mapping| (mapping.0, mapping.1)),
+            text: matches!(element, TemplateElement::RawString(_)),
+            block,
+        });
+
+        let params = iter::once(Parameter::Literal(JsonValue::from(id)))
+            .chain(read_parameters(&element))
+            .collect();
+        push(helper_call(TRACE_HELPER, params));
+        push(element);
+        if block {
+            push(helper_call(TRACE_END_HELPER, vec![]));
+        }
+    }
+
+    template.elements = instrumented;
+    if !instrumented_mapping.is_empty() {
+        template.mapping = Some(instrumented_mapping);
+    }
+}
+
+fn helper_call(name: &str, params: Vec<Parameter>) -> TemplateElement {
+    TemplateElement::Expression(Box::new(HelperTemplate {
+        name: Parameter::Name(name.to_owned()),
+        params,
+        hash: HashMap::new(),
+        block_param: None,
+        template: None,
+        inverse: None,
+        block: false,
+    }))
+}
+
+/// The parameters of an element which are paths in the data, including
+/// the ones of its subexpressions.
+fn read_parameters(element: &TemplateElement) -> Vec<Parameter> {
+    let mut paths = vec![];
+    match element {
+        TemplateElement::HTMLExpression(parameter) => add_paths(parameter, &mut paths),
+        TemplateElement::Expression(helper)
+            if helper.params.is_empty() && helper.hash.is_empty() =>
+        {
+            // Either a variable or a helper without parameters.
+            match &helper.name {
+                Parameter::Name(name) => {
+                    paths.extend(handlebars::Path::parse(name).ok().map(Parameter::Path))
+                }
+                parameter => add_paths(parameter, &mut paths),
+            }
+        }
+        TemplateElement::Expression(helper) | TemplateElement::HelperBlock(helper) => {
+            for parameter in helper.params.iter().chain(helper.hash.values()) {
+                add_paths(parameter, &mut paths);
+            }
+        }
+        TemplateElement::PartialExpression(decorator)
+        | TemplateElement::PartialBlock(decorator) => {
+            for parameter in decorator.params.iter().chain(decorator.hash.values()) {
+                add_paths(parameter, &mut paths);
+            }
+        }
+        _ => {}
+    }
+    paths
+}
+
+fn add_paths(parameter: &Parameter, paths: &mut Vec<Parameter>) {
+    match parameter {
+        Parameter::Path(_) => paths.push(parameter.clone()),
+        Parameter::Subexpression(Subexpression { element }) => {
+            if let TemplateElement::Expression(helper) = element.as_ref() {
+                for parameter in helper.params.iter().chain(helper.hash.values()) {
+                    add_paths(parameter, paths);
+                }
+            }
+        }
+        Parameter::Name(_) | Parameter::Literal(_) => {}
+    }
+}
+
+/// Records the paths of its parameters but the first, the id of the
+/// element, and writes a marker with the index of its record.
+struct TraceHelper {
+    events: Arc<Mutex<Vec<TraceEvent>>>,
+}
+
+impl HelperDef for TraceHelper {
+    fn call<'reg: 'rc, 'rc>(
+        &self,
+        h: &Helper<'reg, 'rc>,
+        _: &'reg Handlebars,
+        _: &'rc Context,
+        _: &mut RenderContext<'reg, 'rc>,
+        out: &mut dyn Output,
+    ) -> HelperResult {
+        let element = h
+            .param(0)
+            .and_then(|id| id.value().as_u64())
+            .ok_or_else(|| RenderError::new("Invalid element traced."))?;
+        let reads = h
+            .params()
+            .iter()
+            .skip(1)
+            .filter_map(|param| param.context_path())
+            .map(|path| Path::from(path.join(".")))
+            .collect();
+
+        let mut events = self.events.lock().unwrap();
+        out.write(&format!("{}{}{}", START_MARKER, events.len(), CLOSE_MARKER))?;
+        events.push(TraceEvent {
+            element: element as usize,
+            reads,
+        });
+        Ok(())
+    }
+}
+
+/// Marks the end of the last block which was started.
+struct TraceEndHelper;
+
+impl HelperDef for TraceEndHelper {
+    fn call<'reg: 'rc, 'rc>(
+        &self,
+        _: &Helper<'reg, 'rc>,
+        _: &'reg Handlebars,
+        _: &'rc Context,
+        _: &mut RenderContext<'reg, 'rc>,
+        out: &mut dyn Output,
+    ) -> HelperResult {
+        out.write(&END_MARKER.to_string())?;
+        Ok(())
+    }
+}
+
+/// Removes the markers of the output, and returns the spans they delimit.
+fn strip_markers(
+    output: &str,
+    events: Vec<TraceEvent>,
+    elements: &[TracedElement],
+) -> (String, SourceMap) {
+    let mut events: Vec<Option<TraceEvent>> = events.into_iter().map(Some).collect();
+    let mut content = String::with_capacity(output.len());
+    let mut spans: Vec<TracedSpan> = vec![];
+    // The span of the last text or expression, which ends at the next
+    // marker, and the spans of the blocks, which end at their end marker.
+    let mut leaf: Option<usize> = None;
+    let mut blocks: Vec<usize> = vec![];
+
+    let mut rest = output;
+    while let Some(index) = rest.find([START_MARKER, END_MARKER]) {
+        content.push_str(&rest[..index]);
+        let marker = rest[index..].chars().next().unwrap();
+        rest = &rest[index + marker.len_utf8()..];
+
+        if let Some(leaf) = leaf.take() {
+            spans[leaf].end = content.len();
+        }
+        if marker == END_MARKER {
+            if let Some(block) = blocks.pop() {
+                spans[block].end = content.len();
+            }
+            continue;
+        }
+
+        let (event, after) = match rest.split_once(CLOSE_MARKER) {
+            Some((event, after)) => (event, after),
+            None => break,
+        };
+        rest = after;
+        let event = match event
+            .parse()
+            .ok()
+            .and_then(|i: usize| events.get_mut(i)?.take())
+        {
+            Some(event) => event,
+            None => continue,
+        };
+        spans.push(TracedSpan {
+            start: content.len(),
+            end: content.len(),
+            element: event.element,
+            reads: event.reads,
+        });
+        if elements[event.element].block {
+            blocks.push(spans.len() - 1);
+        } else {
+            leaf = Some(spans.len() - 1);
+        }
+    }
+    content.push_str(rest);
+    for span in leaf.into_iter().chain(blocks) {
+        spans[span].end = content.len();
+    }
+
+    let source_map = SourceMap {
+        elements: elements.to_vec(),
+        spans,
+    };
+    (content, source_map)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn scenario(template: &str) -> Scenario {
+        serde_json::from_value(json!({"template": template, "inputs": []})).unwrap()
+    }
+
+    #[test]
+    fn traced_render_is_unchanged() {
+        let template = "{{#each items as |item|}}\n- {{item.name}}{{~#if item.done}} (done){{/if}}\n{{/each}}\n{{> main_partial}}";
+        let mut scenario = scenario(template);
+        scenario.templates.push(
+            serde_json::from_value(json!({"name": "main_partial", "template": "{{title}}"}))
+                .unwrap(),
+        );
+        let data = json!({"title": "T", "items": [{"name": "a", "done": true}, {"name": "b"}]});
+
+        let engine = HandlebarsEngine::with_scenario(&scenario).unwrap();
+        let tracer = Tracer::new(&scenario).unwrap();
+        let (content, _) = tracer.render("main", &data).unwrap();
+        assert_eq!(content, engine.render("main", &data).unwrap());
+        assert_eq!(content, "\n- a (done)\n\n- b\n\nT");
+    }
+
+    #[test]
+    fn line_sources() {
+        let scenario = scenario("Title: {{title}}\n{{#each items}}\n  item {{name}}\n{{/each}}");
+        let data = json!({"title": "T", "items": [{"name": "a"}, {"name": "b"}]});
+        let (content, source_map) = Tracer::new(&scenario)
+            .unwrap()
+            .render("main", &data)
+            .unwrap();
+        assert_eq!(content, "Title: T\n\n  item a\n\n  item b\n");
+
+        let sources = source_map.line_sources(&content, 0);
+        let lines: Vec<usize> = sources
+            .template_lines
+            .iter()
+            .map(|line| line.line)
+            .collect();
+        assert_eq!(lines, vec![1]);
+        assert_eq!(sources.reads, vec![Path::from("title")]);
+
+        let sources = source_map.line_sources(&content, 4);
+        let lines: Vec<usize> = sources
+            .template_lines
+            .iter()
+            .map(|line| line.line)
+            .collect();
+        assert_eq!(lines, vec![3]);
+        assert_eq!(
+            sources.reads,
+            vec![Path::from("items"), Path::from("items.1.name")]
+        );
+    }
+
+    #[test]
+    fn fragments_reading() {
+        let scenario = scenario("{{a}}, {{b.c}} {{#each d}}{{this}}{{/each}} {{b}}");
+        let data = json!({"a": "A", "b": {"c": "C"}, "d": [1, 2]});
+        let (content, source_map) = Tracer::new(&scenario)
+            .unwrap()
+            .render("main", &data)
+            .unwrap();
+        let fragments = |path: &str| -> Vec<&str> {
+            source_map
+                .fragments_reading(&Path::from(path))
+                .into_iter()
+                .map(|range| &content[range])
+                .collect()
+        };
+
+        assert_eq!(fragments("a"), vec!["A"]);
+        assert_eq!(fragments("b.c"), vec!["C", "[object]"]);
+        assert_eq!(fragments("d"), vec!["12"]);
+        assert_eq!(fragments("d.1"), vec!["2"]);
+    }
+}
@@ -0,0 +1,139 @@
+use crate::{
+    diff::{DiffLine, LineChange},
+    highlight::Highlight,
+    prelude::*,
+};
+use std::{iter, ops::Range};
+
+/// What is drawn over the highlighted code.
+#[derive(Default)]
+pub struct CodeMarks {
+    /// Sorted and disjoint byte ranges of the code to mark.
+    pub ranges: Vec<Range<usize>>,
+    pub selected_line: Option<usize>,
+    /// Called with the index of the clicked line, starting at 0.
+    pub on_click_line: Option<Callback<usize>>,
+}
+
+/// Renders a rendered output with its highlights and line numbers.
+///
+/// Highlights and marks spanning several lines, eg. block comments, are
+/// split so that each line is its own element.
+pub fn render_code(content: &str, highlights: &[Highlight], marks: &CodeMarks) -> Html {
+    let mut line_start = 0;
+    let lines: Vec<Html> = content
+        .split('\n')
+        .enumerate()
+        .map(|(index, line)| {
+            let line_end = line_start + line.len();
+            let segments = render_line(content, line_start..line_end, highlights, &marks.ranges);
+            line_start = line_end + 1;
+
+            let class = if marks.selected_line == Some(index) {
+                "code-line is-selected"
+            } else {
+                "code-line"
+            };
+            let on_click = match &marks.on_click_line {
+                Some(callback) => callback.reform(move |_| index),
+                None => Callback::noop(),
+            };
+            html! {
+                <span class=class onclick=on_click>
+                    <span class="line-number">{ index + 1 }</span>
+                    { for segments }
+                </span>
+            }
+        })
+        .collect();
+
+    html! {
+        <pre class="code">{ for lines }</pre>
+    }
+}
+
+/// Cuts a line at the bounds of the highlights and marks covering it.
+fn render_line(
+    content: &str,
+    line: Range<usize>,
+    highlights: &[Highlight],
+    marks: &[Range<usize>],
+) -> Vec<Html> {
+    let highlights =
+        &highlights[highlights.partition_point(|highlight| highlight.end <= line.start)..];
+    let highlights =
+        &highlights[..highlights.partition_point(|highlight| highlight.start < line.end)];
+    let marks = &marks[marks.partition_point(|mark| mark.end <= line.start)..];
+    let marks = &marks[..marks.partition_point(|mark| mark.start < line.end)];
+
+    let mut cuts: Vec<usize> = highlights
+        .iter()
+        .flat_map(|highlight| iter::once(highlight.start).chain(iter::once(highlight.end)))
+        .chain(
+            marks
+                .iter()
+                .flat_map(|mark| iter::once(mark.start).chain(iter::once(mark.end))),
+        )
+        .filter(|cut| line.contains(cut))
+        .chain(iter::once(line.start).chain(iter::once(line.end)))
+        .collect();
+    cuts.sort_unstable();
+    cuts.dedup();
+
+    cuts.windows(2)
+        .map(|cut| {
+            let (start, end) = (cut[0], cut[1]);
+            let text = &content[start..end];
+            let highlight = highlights
+                .iter()
+                .find(|highlight| highlight.start <= start && start < highlight.end);
+            let marked = marks.iter().any(|mark| mark.contains(&start));
+            match (highlight, marked) {
+                (None, false) => html! { { text } },
+                (Some(highlight), false) => {
+                    html! { <span class=highlight.kind.class()>{ text }</span> }
+                }
+                (highlight, true) => {
+                    let class = match highlight {
+                        Some(highlight) => format!("{} is-marked", highlight.kind.class()),
+                        None => "is-marked".to_owned(),
+                    };
+                    html! { <span class=class>{ text }</span> }
+                }
+            }
+        })
+        .collect()
+}
+
+/// Renders a diff, numbering the lines of the new output. Removed lines are
+/// not numbered.
+pub fn render_diff(lines: &[DiffLine]) -> Html {
+    let mut number = 0;
+    let lines: Vec<Html> = lines
+        .iter()
+        .map(|line| {
+            let (class, marker) = match line.change {
+                LineChange::Unchanged => ("code-line", ' '),
+                LineChange::Inserted => ("code-line diff-inserted", '+'),
+                LineChange::Removed => ("code-line diff-removed", '-'),
+            };
+            let number = if line.change == LineChange::Removed {
+                String::new()
+            } else {
+                number += 1;
+                number.to_string()
+            };
+            html! {
+                <span class=class>
+                    <span class="line-number">{ number }</span>
+                    <span class="diff-marker">{ marker }</span>
+                    { &line.content }
+                </span>
+            }
+        })
+        .collect();
+
+    html! {
+        <pre class="code">{ for lines }</pre>
+    }
+}
@@ -1,19 +1,100 @@
-use crate::{app, for_all_inputtypes_variants, inputs::*, prelude::*, InputsData, Path};
+use crate::{app, inputs::*, prelude::*, InputsData, Path};
+use lazy_static::lazy_static;
+use std::{collections::HashMap, sync::RwLock};
 
-type AppComponentLink = yew::ComponentLink<app::App>;
+pub type AppComponentLink = yew::ComponentLink<app::App>;
 
+/// Renders an input as a form field, sending `app::Msg::EditedInput` when
+/// it's edited.
+///
+/// The inputs of a kind are rendered with the view registered for it with
+/// [`register_input_view`].
 pub trait RenderableInput {
-    fn render(&self, key_base: &Path, inputs_data: &InputsData, link: &AppComponentLink) -> Html;
+    fn render(
+        &self,
+        key_base: &Path,
+        inputs_data: &InputsData,
+        edited: &[Path],
+        link: &AppComponentLink,
+    ) -> Html;
 }
 
 impl RenderableInput for InputTypes {
-    fn render(&self, key_base: &Path, inputs_data: &InputsData, link: &AppComponentLink) -> Html {
-        for_all_inputtypes_variants! { self, i => i.render(key_base, inputs_data, link) }
+    fn render(
+        &self,
+        key_base: &Path,
+        inputs_data: &InputsData,
+        edited: &[Path],
+        link: &AppComponentLink,
+    ) -> Html {
+        let view = INPUT_VIEWS
+            .read()
+            .expect("The input views registry is poisoned.")
+            .get(self.type_name())
+            .copied();
+        match view {
+            Some(render) => render(self, key_base, inputs_data, edited, link),
+            None => render_missing_view(self),
+        }
+    }
+}
+
+type InputView = fn(&InputTypes, &Path, &InputsData, &[Path], &AppComponentLink) -> Html;
+
+lazy_static! {
+    static ref INPUT_VIEWS: RwLock<HashMap<&'static str, InputView>> = {
+        let builtins: Vec<(&'static str, InputView)> = vec![
+            ("text", render_as::<TextInput>),
+            ("boolean", render_as::<BooleanInput>),
+            ("number", render_as::<NumberInput>),
+            ("group", render_as::<GroupInput>),
+            ("list", render_as::<ListInput>),
+        ];
+        RwLock::new(builtins.into_iter().collect())
+    };
+}
+
+/// Renders the inputs of the kind registered as `type_name` (see
+/// [`register_input_kind`]) with the view of `T`, replacing any view
+/// previously registered for it.
+pub fn register_input_view<T: Input + RenderableInput + 'static>(type_name: &'static str) {
+    INPUT_VIEWS
+        .write()
+        .expect("The input views registry is poisoned.")
+        .insert(type_name, render_as::<T>);
+}
+
+fn render_as<T: Input + RenderableInput + 'static>(
+    input: &InputTypes,
+    key_base: &Path,
+    inputs_data: &InputsData,
+    edited: &[Path],
+    link: &AppComponentLink,
+) -> Html {
+    match input.downcast_ref::<T>() {
+        Some(view) => view.render(key_base, inputs_data, edited, link),
+        None => render_missing_view(input),
+    }
+}
+
+fn render_missing_view(input: &InputTypes) -> Html {
+    html! {
+        <div class="field">
+            <p class="help is-danger">
+                { format!("No view is registered for the '{}' input '{}'.", input.type_name(), input.name()) }
+            </p>
+        </div>
     }
 }
 
 impl RenderableInput for TextInput {
-    fn render(&self, key_base: &Path, inputs_data: &InputsData, link: &AppComponentLink) -> Html {
+    fn render(
+        &self,
+        key_base: &Path,
+        inputs_data: &InputsData,
+        edited: &[Path],
+        link: &AppComponentLink,
+    ) -> Html {
         let key = key_base + self.key();
 
         let key_inner = key.clone();
@@ -34,9 +115,11 @@ impl RenderableInput for TextInput {
             String::default()
         };
 
+        let (on_enter, on_leave) = on_hover(&key, link);
+
         html! {
-            <div class="field">
-                <label class="label">{ self.name() }</label>
+            <div class="field" onmouseenter=on_enter onmouseleave=on_leave>
+                <label class="label">{ self.name() }{ render_required_marker(self.required()) }</label>
                 <div class="control">
                     <input
                         class="input"
@@ -47,23 +130,30 @@ impl RenderableInput for TextInput {
                         />
                 </div>
                 { render_description(self.description()) }
+                { render_validation_errors(&key, edited, || self.validate(key_base, inputs_data)) }
             </div>
         }
     }
 }
 
 impl RenderableInput for GroupInput {
-    fn render(&self, key_base: &Path, inputs_data: &InputsData, link: &AppComponentLink) -> Html {
+    fn render(
+        &self,
+        key_base: &Path,
+        inputs_data: &InputsData,
+        edited: &[Path],
+        link: &AppComponentLink,
+    ) -> Html {
         let key = key_base + self.key();
         html! {
             <div class="field input-group">
-                <p class="label">{ self.name() }</p>
+                <p class="label">{ self.name() }{ render_required_marker(self.required()) }</p>
                 { render_description(self.description()) }
                 <div class="input-group-children">
                     { for self
                         .inputs
                         .iter()
-                        .map(|input| input.render(&key, &inputs_data, &link))
+                        .map(|input| input.render(&key, &inputs_data, edited, &link))
                     }
                 </div>
             </div>
@@ -72,7 +162,13 @@ impl RenderableInput for GroupInput {
 }
 
 impl RenderableInput for NumberInput {
-    fn render(&self, key_base: &Path, inputs_data: &InputsData, link: &AppComponentLink) -> Html {
+    fn render(
+        &self,
+        key_base: &Path,
+        inputs_data: &InputsData,
+        edited: &[Path],
+        link: &AppComponentLink,
+    ) -> Html {
         let key = key_base + self.key();
         let key_callback = key.clone();
         let on_input = link.callback(move |input_data: InputData| {
@@ -108,9 +204,11 @@ impl RenderableInput for NumberInput {
             .map(ToString::to_string)
             .unwrap_or_default();
 
+        let (on_enter, on_leave) = on_hover(&key, link);
+
         html! {
-            <div class="field">
-                <label class="label">{ self.name() }</label>
+            <div class="field" onmouseenter=on_enter onmouseleave=on_leave>
+                <label class="label">{ self.name() }{ render_required_marker(self.required()) }</label>
                 <div class="control">
                     <input
                         class="input"
@@ -124,13 +222,20 @@ impl RenderableInput for NumberInput {
                         />
                 </div>
                 { render_description(self.description()) }
+                { render_validation_errors(&key, edited, || self.validate(key_base, inputs_data)) }
             </div>
         }
     }
 }
 
 impl RenderableInput for ListInput {
-    fn render(&self, key_base: &Path, inputs_data: &InputsData, link: &AppComponentLink) -> Html {
+    fn render(
+        &self,
+        key_base: &Path,
+        inputs_data: &InputsData,
+        edited: &[Path],
+        link: &AppComponentLink,
+    ) -> Html {
         let key = key_base + self.key();
 
         let list_data = inputs_data.get_at(&key);
@@ -159,7 +264,7 @@ impl RenderableInput for ListInput {
                     { for self
                         .inputs
                         .iter()
-                        .map(|input| input.render(&key_base, &inputs_data, &link))
+                        .map(|input| input.render(&key_base, &inputs_data, edited, &link))
                     }
                 </div>
             }
@@ -167,7 +272,7 @@ impl RenderableInput for ListInput {
 
         html! {
             <div class="field input-group">
-                <p class="label">{ self.name() }</p>
+                <p class="label">{ self.name() }{ render_required_marker(self.required()) }</p>
                 { render_description(self.description()) }
 
                 { for (0..len)
@@ -192,7 +297,13 @@ impl RenderableInput for ListInput {
 }
 
 impl RenderableInput for BooleanInput {
-    fn render(&self, key_base: &Path, inputs_data: &InputsData, link: &AppComponentLink) -> Html {
+    fn render(
+        &self,
+        key_base: &Path,
+        inputs_data: &InputsData,
+        _: &[Path],
+        link: &AppComponentLink,
+    ) -> Html {
         let key = key_base + self.key();
 
         let key_inner = key.clone();
@@ -212,17 +323,41 @@ impl RenderableInput for BooleanInput {
         };
         //let color_class = if checked { "is-success" } else { "is-danger" };
         let id = format!("input_boolean_{}", key);
+        let (on_enter, on_leave) = on_hover(&key, link);
 
         html! {
-            <div class="field">
+            <div class="field" onmouseenter=on_enter onmouseleave=on_leave>
                 <input id=id name=id type="checkbox" class="switch" checked=checked onclick=on_click(!checked) />
-                <label for=id class="label">{ self.name() }</label>
+                <label for=id class="label">{ self.name() }{ render_required_marker(self.required()) }</label>
                 { render_description(self.description()) }
             </div>
         }
     }
 }
 
+/// Marks the fragments of the outputs written from an input while it is
+/// hovered.
+fn on_hover(
+    key: &Path,
+    link: &AppComponentLink,
+) -> (Callback<MouseEnterEvent>, Callback<MouseLeaveEvent>) {
+    let key = key.clone();
+    (
+        link.callback(move |_: MouseEnterEvent| app::Msg::HoverInput(Some(key.clone()))),
+        link.callback(|_: MouseLeaveEvent| app::Msg::HoverInput(None)),
+    )
+}
+
+fn render_required_marker(required: bool) -> Html {
+    if required {
+        html! {
+            <span class="has-text-danger" title="Required">{ " *" }</span>
+        }
+    } else {
+        html! {}
+    }
+}
+
 fn render_description<T: AsRef<str>>(description: Option<T>) -> Html {
     if let Some(text) = description {
         html! {
@@ -232,3 +367,21 @@ fn render_description<T: AsRef<str>>(description: Option<T>) -> Html {
         html! {}
     }
 }
+
+/// Renders the errors of an input once it has been edited, rather than
+/// every required field as soon as a scenario is loaded.
+fn render_validation_errors(
+    key: &Path,
+    edited: &[Path],
+    validate: impl FnOnce() -> Vec<ValidationError>,
+) -> Html {
+    if !edited.contains(key) {
+        return html! {};
+    }
+    let errors = validate();
+    html! {
+        { for errors.iter().map(|error| html! {
+            <p class="help is-danger">{ &error.message }</p>
+        }) }
+    }
+}
@@ -1,3 +1,5 @@
+mod code;
 mod inputs;
 
-pub use inputs::RenderableInput;
+pub use code::{render_code, render_diff, CodeMarks};
+pub use inputs::{register_input_view, AppComponentLink, RenderableInput};
@@ -0,0 +1,197 @@
+//! A minimal writer of zip archives, storing files without compression,
+//! to download several rendered files at once.
+
+use anyhow::{bail, Result};
+use std::{
+    collections::HashSet,
+    path::{Component, Path},
+};
+
+const LOCAL_FILE_HEADER: u32 = 0x0403_4b50;
+const CENTRAL_DIRECTORY_HEADER: u32 = 0x0201_4b50;
+const END_OF_CENTRAL_DIRECTORY: u32 = 0x0605_4b50;
+/// Version 2.0, the first one supporting directories.
+const VERSION: u16 = 20;
+/// Bit 11 of the general purpose flags: the file names are UTF-8.
+const UTF8_NAMES: u16 = 1 << 11;
+/// 1980-01-01 00:00, the earliest MS-DOS date.
+const DOS_DATE: u16 = (1 << 5) | 1;
+const DOS_TIME: u16 = 0;
+
+/// Builds a zip archive of the given files, as `(path, content)`. The paths
+/// must be relative and stay in the archive, and be distinct.
+pub fn zip_files<'a>(files: impl IntoIterator<Item = (&'a str, &'a [u8])>) -> Result<Vec<u8>> {
+    let mut archive = vec![];
+    let mut central_directory = vec![];
+    let mut count: u16 = 0;
+    let mut paths = HashSet::new();
+
+    for (path, content) in files {
+        let path = archive_path(path)?;
+        if !paths.insert(path.clone()) {
+            bail!("Several files have the name '{}'.", path);
+        }
+        let path = path.as_str();
+        let offset = archive.len() as u32;
+        let crc = crc32(content);
+
+        push_u32(&mut archive, LOCAL_FILE_HEADER);
+        push_entry_fields(&mut archive, path, content, crc);
+        push_u16(&mut archive, 0); // Extra field length
+        archive.extend_from_slice(path.as_bytes());
+        archive.extend_from_slice(content);
+
+        push_u32(&mut central_directory, CENTRAL_DIRECTORY_HEADER);
+        push_u16(&mut central_directory, VERSION); // Made by
+        push_entry_fields(&mut central_directory, path, content, crc);
+        push_u16(&mut central_directory, 0); // Extra field length
+        push_u16(&mut central_directory, 0); // Comment length
+        push_u16(&mut central_directory, 0); // Disk number
+        push_u16(&mut central_directory, 0); // Internal attributes
+        push_u32(&mut central_directory, 0); // External attributes
+        push_u32(&mut central_directory, offset);
+        central_directory.extend_from_slice(path.as_bytes());
+
+        count += 1;
+    }
+
+    let central_directory_offset = archive.len() as u32;
+    archive.extend_from_slice(&central_directory);
+
+    push_u32(&mut archive, END_OF_CENTRAL_DIRECTORY);
+    push_u16(&mut archive, 0); // This disk
+    push_u16(&mut archive, 0); // Disk with the central directory
+    push_u16(&mut archive, count); // Entries on this disk
+    push_u16(&mut archive, count); // Total entries
+    push_u32(&mut archive, central_directory.len() as u32);
+    push_u32(&mut archive, central_directory_offset);
+    push_u16(&mut archive, 0); // Comment length
+    Ok(archive)
+}
+
+/// Checks that a path stays in the archive, as in the CLI, and returns it
+/// without its `.` components, to tell the duplicates.
+fn archive_path(path: &str) -> Result<String> {
+    let mut segments = vec![];
+    for component in Path::new(path).components() {
+        match component {
+            Component::Normal(segment) => segments.push(segment.to_string_lossy()),
+            Component::CurDir => {}
+            _ => bail!("The file name '{}' is outside of the archive.", path),
+        }
+    }
+    if segments.is_empty() {
+        bail!("The file name '{}' is empty.", path);
+    }
+    Ok(segments.join("/"))
+}
+
+/// The fields shared by the local and central headers, from the version
+/// needed to extract up to the file name length.
+fn push_entry_fields(buffer: &mut Vec<u8>, path: &str, content: &[u8], crc: u32) {
+    push_u16(buffer, VERSION);
+    push_u16(buffer, UTF8_NAMES);
+    push_u16(buffer, 0); // Stored, no compression
+    push_u16(buffer, DOS_TIME);
+    push_u16(buffer, DOS_DATE);
+    push_u32(buffer, crc);
+    push_u32(buffer, content.len() as u32); // Compressed size
+    push_u32(buffer, content.len() as u32);
+    push_u16(buffer, path.len() as u16);
+}
+
+fn push_u16(buffer: &mut Vec<u8>, value: u16) {
+    buffer.extend_from_slice(&value.to_le_bytes());
+}
+
+fn push_u32(buffer: &mut Vec<u8>, value: u32) {
+    buffer.extend_from_slice(&value.to_le_bytes());
+}
+
+/// CRC-32 (IEEE), as required by the zip format.
+fn crc32(data: &[u8]) -> u32 {
+    !data.iter().fold(!0, |crc, &byte| {
+        (0..8).fold(crc ^ u32::from(byte), |crc, _| {
+            if crc & 1 == 1 {
+                (crc >> 1) ^ 0xEDB8_8320
+            } else {
+                crc >> 1
+            }
+        })
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::convert::TryInto;
+
+    fn u16_at(buffer: &[u8], offset: usize) -> u16 {
+        u16::from_le_bytes(buffer[offset..offset + 2].try_into().unwrap())
+    }
+
+    fn u32_at(buffer: &[u8], offset: usize) -> u32 {
+        u32::from_le_bytes(buffer[offset..offset + 4].try_into().unwrap())
+    }
+
+    #[test]
+    fn crc32_check_value() {
+        assert_eq!(crc32(b""), 0);
+        assert_eq!(crc32(b"123456789"), 0xCBF4_3926);
+    }
+
+    #[test]
+    fn empty_archive() {
+        let archive = zip_files(vec![]).unwrap();
+        assert_eq!(archive.len(), 22);
+        assert_eq!(u32_at(&archive, 0), END_OF_CENTRAL_DIRECTORY);
+    }
+
+    #[test]
+    fn archive_layout() {
+        let archive = zip_files(vec![
+            ("Cargo.toml", &b"[package]"[..]),
+            ("./src/main.rs", &b"fn main() {}"[..]),
+        ])
+        .unwrap();
+
+        let end = archive.len() - 22;
+        assert_eq!(u32_at(&archive, end), END_OF_CENTRAL_DIRECTORY);
+        assert_eq!(u16_at(&archive, end + 10), 2);
+        let central_directory = u32_at(&archive, end + 16) as usize;
+        assert_eq!(u32_at(&archive, end + 12) as usize, end - central_directory);
+
+        // Second entry of the central directory, after the 46 bytes of the
+        // first header and its 10 bytes long name.
+        let entry = central_directory + 46 + 10;
+        assert_eq!(u32_at(&archive, entry), CENTRAL_DIRECTORY_HEADER);
+        assert_eq!(u32_at(&archive, entry + 16), crc32(b"fn main() {}"));
+        assert_eq!(&archive[entry + 46..entry + 57], b"src/main.rs");
+
+        let local = u32_at(&archive, entry + 42) as usize;
+        assert_eq!(local, 30 + 10 + 9);
+        assert_eq!(u32_at(&archive, local), LOCAL_FILE_HEADER);
+        assert_eq!(
+            &archive[local + 30 + 11..central_directory],
+            b"fn main() {}"
+        );
+    }
+
+    #[test]
+    fn invalid_paths() {
+        for path in &[
+            "../README.md",
+            "docs/../../README.md",
+            "/etc/passwd",
+            "",
+            "./",
+        ] {
+            assert!(zip_files(vec![(*path, &b""[..])]).is_err(), "{}", path);
+        }
+        let error = zip_files(vec![("src/lib.rs", &b""[..]), ("./src/lib.rs", &b""[..])]);
+        assert_eq!(
+            error.unwrap_err().to_string(),
+            "Several files have the name 'src/lib.rs'."
+        );
+    }
+}